@@ -0,0 +1,89 @@
+//! Shell completions. The scripts printed by `--completions` call back into nur
+//! as the user types, so that task names are completed from the Nurfile
+//! in the current directory, rather than the one around when the script was made.
+
+use std::{ffi::OsString, path::PathBuf};
+
+use clap_complete::{
+    engine::CompletionCandidate,
+    env::{Bash, EnvCompleter, Fish, Zsh},
+};
+
+/// The environment variable which the completion scripts set when calling nur.
+const VAR: &str = "COMPLETE";
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    fn completer(self) -> &'static dyn EnvCompleter {
+        match self {
+            Shell::Bash => &Bash,
+            Shell::Zsh => &Zsh,
+            Shell::Fish => &Fish,
+        }
+    }
+}
+
+/// Answers a request for completions from one of the scripts, and exits.
+/// Otherwise, does nothing.
+pub fn complete() {
+    clap_complete::CompleteEnv::with_factory(<crate::Cli as clap::CommandFactory>::command)
+        .var(VAR)
+        .shells(clap_complete::env::Shells(&[&Bash, &Zsh, &Fish]))
+        .complete();
+}
+
+/// Writes the script which registers completions with the shell.
+pub fn write_script(shell: Shell, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+    shell
+        .completer()
+        .write_registration(VAR, "nur", "nur", &completer()?, out)
+}
+
+/// The command which the script should run to get completions: this same nur.
+fn completer() -> std::io::Result<String> {
+    let arg0 = PathBuf::from(
+        std::env::args_os()
+            .next()
+            .unwrap_or_else(|| OsString::from("nur")),
+    );
+
+    // a bare name is found on the PATH, but a relative path would
+    // only work from the current directory
+    let path = if arg0.components().count() > 1 && arg0.is_relative() {
+        std::env::current_dir()?.join(arg0)
+    } else {
+        arg0
+    };
+
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// The tasks in the nearest Nurfile, with their descriptions.
+pub fn task_names() -> Vec<CompletionCandidate> {
+    let Ok(cwd) = std::env::current_dir() else {
+        return Vec::new();
+    };
+
+    // there is nowhere to report errors while completing
+    let Ok((_, config)) = nur_lib::nurfile::load_config(&cwd, None) else {
+        return Vec::new();
+    };
+
+    config
+        .tasks
+        .into_iter()
+        .map(|(name, task)| {
+            let help = Some(task.description)
+                .filter(|description| !description.is_empty())
+                .map(Into::into);
+
+            CompletionCandidate::new(name).help(help)
+        })
+        .collect()
+}
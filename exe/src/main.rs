@@ -1,39 +1,221 @@
 use clap::Parser;
 use miette::IntoDiagnostic;
-use std::{collections::BTreeSet, path::PathBuf};
+use std::path::PathBuf;
+
+mod completions;
 
 use nur_lib::{
     commands,
-    nurfile::{OutputOptions, OutputStyle, PrefixStyle},
+    nurfile::{CiProvider, OutputOptions, OutputStyle},
+    output::ColorChoice,
 };
 
+const EXIT_CODES: &str = "\
+Exit codes:
+  0       All tasks succeeded
+  1       More than one task failed, or something else went wrong
+  2       The command line was invalid
+  3       The Nurfile could not be found, read, or understood
+  4       The tasks depend upon each other in a cycle
+  5       A task does not exist
+  128+N   nur was stopped by signal N
+
+When exactly one task failed, nur exits with the exit code of the command
+which failed, or 128+N if the command was killed by signal N. These may
+coincide with the codes above. An exit code which would be seen as 0,
+such as 256, is reported as 1.
+
+On SIGINT or SIGTERM, running tasks are cancelled and nur waits for them.
+A second signal kills any tasks still running, without waiting for them.";
+
 /// A robust task runner.
 #[derive(clap::Parser)]
-#[clap(author, version, about, long_about = None)]
+#[clap(author, version, about, long_about = None, after_long_help = EXIT_CODES)]
+#[clap(group(clap::ArgGroup::new("formatted").args(["list", "dry_run"])))]
 struct Cli {
     /// Names of the tasks to run.
-    #[clap(exclusive = true)]
+    ///
+    /// Separate tasks with `then` to run them one after another, as in `nur clean then build`.
+    #[clap(add = clap_complete::ArgValueCandidates::new(completions::task_names))]
     task_names: Vec<String>,
 
+    /// Run the requested tasks one after another, in the order given.
+    #[clap(long, conflicts_with_all = &["init", "list", "check"])]
+    serial: bool,
+
+    /// Extra arguments to pass to the task being run, available to its commands as `"$@"`.
+    #[clap(last = true)]
+    args: Vec<String>,
+
     /// Create a fresh Nurfile.
     #[clap(long, conflicts_with_all = &["list", "check", "task_names"])]
     init: bool,
 
     /// List all the tasks available in the Nurfile.
-    #[clap(long, short, conflicts_with_all = &["init", "task_names", "check", "dry_run"])]
-    list: bool,
+    ///
+    /// If a pattern is given, only tasks whose names match are listed. `*` matches any text
+    /// and `?` any single character; a pattern without either matches names containing it.
+    #[clap(
+        long,
+        short,
+        num_args = 0..=1,
+        value_name = "PATTERN",
+        conflicts_with_all = &["init", "task_names", "check", "dry_run"]
+    )]
+    list: Option<Option<String>>,
+
+    /// Show the tasks that each listed task depends upon, as a tree.
+    #[clap(long, requires = "list", conflicts_with = "format")]
+    tree: bool,
 
     /// Syntax check the Nurfile and its shell commands.
     #[clap(long, conflicts_with_all = &["init", "task_names", "list", "dry_run"])]
     check: bool,
 
+    /// Print the graph of tasks and their dependencies, instead of running them.
+    ///
+    /// If task names are given, only the tasks they depend upon are included.
+    #[clap(
+        long,
+        value_enum,
+        num_args = 0..=1,
+        default_missing_value = "dot",
+        value_name = "FORMAT",
+        conflicts_with_all = &["init", "list", "check", "dry_run", "args"]
+    )]
+    graph: Option<GraphFormat>,
+
     /// Should what would be executed but don’t actually run the commands.
     #[clap(long, conflicts_with_all = &["list", "check"])]
     dry_run: bool,
 
+    /// The format to print the --dry-run plan or the --list of tasks in.
+    #[clap(long, value_enum, default_value_t = Format::Text, requires = "formatted")]
+    format: Format,
+
+    /// Print a summary of all task outcomes after running.
+    ///
+    /// With JSON output, the summary is printed to stderr instead of stdout.
+    #[clap(long, conflicts_with_all = &["init", "list", "check", "graph", "dry_run"])]
+    summary: bool,
+
+    /// Write a JUnit XML report of the run to the given path.
+    #[clap(long, value_name = "PATH", conflicts_with_all = &["init", "list", "check", "graph", "dry_run"])]
+    junit_report: Option<PathBuf>,
+
+    /// Write the output of each task to `<DIR>/<task>.log`.
+    #[clap(long, value_name = "DIR", conflicts_with_all = &["init", "list", "check", "graph", "dry_run"])]
+    log_dir: Option<PathBuf>,
+
+    /// When to color output.
+    #[clap(long, value_enum, value_name = "WHEN", default_value_t = ColorArg::Auto)]
+    color: ColorArg,
+
+    /// Override the output style set in the Nurfile.
+    #[clap(long, value_enum, value_name = "STYLE", conflicts_with_all = &["init", "list", "check", "graph"])]
+    output: Option<OutputArg>,
+
+    /// Run commands on a pseudo-terminal, so that they use colors and progress bars
+    /// as they would interactively. Only supported on Linux.
+    #[clap(long, conflicts_with_all = &["init", "list", "check", "graph", "dry_run"])]
+    pty: bool,
+
     /// Specify which Nurfile to use.
-    #[clap(long)]
+    #[clap(long, value_hint = clap::ValueHint::FilePath)]
     file: Option<PathBuf>,
+
+    /// Print a script which completes arguments, including task names, for the given shell.
+    ///
+    /// For example, add `source <(nur --completions bash)` to ~/.bashrc.
+    #[clap(long, value_enum, value_name = "SHELL", exclusive = true)]
+    completions: Option<completions::Shell>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum Format {
+    Text,
+    Json,
+}
+
+impl From<Format> for commands::Format {
+    fn from(f: Format) -> Self {
+        match f {
+            Format::Text => commands::Format::Text,
+            Format::Json => commands::Format::Json,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum OutputArg {
+    Streamed,
+    Grouped,
+    Json,
+    /// A live view of running tasks, when stdout is a terminal.
+    Dashboard,
+    /// Collapsible sections for GitHub Actions.
+    Github,
+    /// Collapsible sections for GitLab CI.
+    Gitlab,
+    /// Collapsible sections for Azure Pipelines.
+    Azure,
+    /// Collapsible sections for Buildkite.
+    Buildkite,
+    /// Collapsible sections for TeamCity.
+    Teamcity,
+}
+
+impl From<OutputArg> for OutputOptions {
+    fn from(o: OutputArg) -> Self {
+        let style = |style| OutputOptions {
+            style,
+            ..Default::default()
+        };
+
+        match o {
+            OutputArg::Streamed => style(OutputStyle::streamed()),
+            OutputArg::Grouped => style(OutputStyle::grouped()),
+            OutputArg::Json => style(OutputStyle::Json),
+            OutputArg::Dashboard => style(OutputStyle::Dashboard),
+            OutputArg::Github => OutputOptions::ci(CiProvider::GitHubActions),
+            OutputArg::Gitlab => OutputOptions::ci(CiProvider::GitLab),
+            OutputArg::Azure => OutputOptions::ci(CiProvider::AzurePipelines),
+            OutputArg::Buildkite => OutputOptions::ci(CiProvider::Buildkite),
+            OutputArg::Teamcity => OutputOptions::ci(CiProvider::TeamCity),
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum ColorArg {
+    Auto,
+    Always,
+    Never,
+}
+
+impl From<ColorArg> for ColorChoice {
+    fn from(c: ColorArg) -> Self {
+        match c {
+            ColorArg::Auto => ColorChoice::Auto,
+            ColorArg::Always => ColorChoice::Always,
+            ColorArg::Never => ColorChoice::Never,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum GraphFormat {
+    Dot,
+    Mermaid,
+}
+
+impl From<GraphFormat> for commands::GraphFormat {
+    fn from(f: GraphFormat) -> Self {
+        match f {
+            GraphFormat::Dot => commands::GraphFormat::Dot,
+            GraphFormat::Mermaid => commands::GraphFormat::Mermaid,
+        }
+    }
 }
 
 // the 'subcommands' are:
@@ -41,6 +223,8 @@ struct Cli {
 // * --init: create a sample config file
 // * --list: list all tasks
 // * --check: syntax-check the config file
+// * --graph: render the task graph
+// * --completions: print a shell completion script (handled in `run`)
 
 fn build_command(cli: Cli) -> Box<dyn commands::Command> {
     if cli.init {
@@ -54,37 +238,73 @@ fn build_command(cli: Cli) -> Box<dyn commands::Command> {
         return Box::new(commands::Check { nur_file: cli.file });
     }
 
-    if cli.list {
-        return Box::new(commands::List { nur_file: cli.file });
+    if let Some(pattern) = cli.list {
+        return Box::new(commands::List {
+            nur_file: cli.file,
+            pattern,
+            format: cli.format.into(),
+            tree: cli.tree,
+            color: cli.color.into(),
+        });
     }
 
-    // if we are running in a Github action, automatically use a nice format
-    let output_override = if std::env::var_os("GITHUB_ACTIONS") == Some("true".into()) {
-        Some(OutputOptions {
-            prefix: PrefixStyle::NoPrefix,
-            style: OutputStyle::Grouped {
-                separator: String::new(),
-                separator_first: Some("::group::".to_string()),
-                separator_last: Some("::endgroup::".to_string()),
-                deterministic: true,
-                only_on_failure: false,
-            },
-        })
-    } else {
-        None
-    };
+    if let Some(format) = cli.graph {
+        return Box::new(commands::Graph {
+            nur_file: cli.file,
+            task_names: cli.task_names,
+            format: format.into(),
+        });
+    }
 
     Box::new(commands::Task {
         dry_run: cli.dry_run,
+        format: cli.format.into(),
         nur_file: cli.file,
-        task_names: BTreeSet::from_iter(cli.task_names),
-        output_override,
+        task_names: cli.task_names,
+        serial: cli.serial,
+        args: cli.args,
+        output_override: cli.output.map(Into::into),
+        ci: CiProvider::detect(),
+        summary: cli.summary,
+        junit_report: cli.junit_report,
+        log_dir: cli.log_dir,
+        color: cli.color.into(),
+        pty: cli.pty,
     })
 }
 
-fn main() -> miette::Result<()> {
+fn main() -> std::process::ExitCode {
+    match run() {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(report) => {
+            eprintln!("Error: {report:?}");
+            let code = report
+                .downcast_ref::<nur_lib::Error>()
+                .map_or(nur_lib::exit_code::FAILURE, nur_lib::Error::exit_code);
+
+            std::process::ExitCode::from(code)
+        }
+    }
+}
+
+fn run() -> miette::Result<()> {
+    completions::complete();
+
     let cwd = std::env::current_dir().into_diagnostic()?;
     let cli = Cli::parse();
+
+    if let Some(shell) = cli.completions {
+        return completions::write_script(shell, &mut std::io::stdout().lock()).into_diagnostic();
+    }
+
+    // errors should follow the same choice as task output
+    if let ColorArg::Always | ColorArg::Never = cli.color {
+        let color = matches!(cli.color, ColorArg::Always);
+        _ = miette::set_hook(Box::new(move |_| {
+            Box::new(miette::MietteHandlerOpts::new().color(color).build())
+        }));
+    }
+
     let command = build_command(cli);
 
     let ctx = nur_lib::commands::Context {
@@ -1,4 +1,5 @@
 mod check;
+mod graph;
 mod init;
 mod list;
 mod task;
@@ -6,6 +7,7 @@ mod task;
 use std::io::Write;
 
 pub use check::Check;
+pub use graph::{Graph, GraphFormat};
 pub use init::Init;
 pub use list::List;
 pub use task::Task;
@@ -16,10 +18,24 @@ pub struct Context<'a> {
     pub stderr: &'a mut dyn Write,
 }
 
+/// How a command formats what it prints.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Human-readable text.
+    #[default]
+    Text,
+
+    /// JSON, for consumption by other programs.
+    Json,
+}
+
 #[derive(Debug)]
 pub enum Message {
     Out(String),
     Err(String),
+    /// A line which replaces the last line written to the terminal,
+    /// if it was also a progress line. An empty line clears it.
+    Progress(String),
 }
 
 pub trait Command {
@@ -0,0 +1,239 @@
+use std::collections::BTreeSet;
+
+use miette::IntoDiagnostic;
+use petgraph::{graphmap::DiGraphMap, visit::Dfs};
+use rustworkx_core::connectivity::find_cycle;
+
+use crate::nurfile::NurFile;
+
+/// Renders the task dependency graph in a format understood by other tools.
+pub struct Graph {
+    pub nur_file: Option<std::path::PathBuf>,
+    /// If not empty, only the tasks reachable from these are included.
+    pub task_names: Vec<String>,
+    pub format: GraphFormat,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// The Graphviz DOT language.
+    #[default]
+    Dot,
+
+    /// Mermaid flowchart syntax, as rendered by GitHub and others.
+    Mermaid,
+}
+
+const DESCRIPTION_WIDTH: usize = 40;
+
+impl crate::commands::Command for Graph {
+    fn run(&self, ctx: crate::commands::Context) -> miette::Result<()> {
+        let (_, config) = crate::nurfile::load_config(&ctx.cwd, self.nur_file.as_deref())?;
+
+        let graph = self.subgraph(config.dependency_graph())?;
+
+        // a cycle is highlighted rather than reported as an error,
+        // since seeing it is likely the reason for drawing the graph
+        let cycle: BTreeSet<(&str, &str)> = find_cycle(&graph, None).into_iter().collect();
+
+        let rendered = match self.format {
+            GraphFormat::Dot => render_dot(&graph, &config, &cycle),
+            GraphFormat::Mermaid => render_mermaid(&graph, &config, &cycle),
+        };
+
+        ctx.stdout
+            .write_all(rendered.as_bytes())
+            .into_diagnostic()?;
+
+        Ok(())
+    }
+}
+
+impl Graph {
+    /// Restricts the graph to what is reachable from the requested tasks.
+    fn subgraph<'a>(
+        &self,
+        graph: DiGraphMap<&'a str, ()>,
+    ) -> crate::Result<DiGraphMap<&'a str, ()>> {
+        let task_names: Vec<&str> = self
+            .task_names
+            .iter()
+            .map(|name| name.as_str())
+            .filter(|name| *name != crate::commands::task::THEN_KEYWORD)
+            .collect();
+
+        if task_names.is_empty() {
+            return Ok(graph);
+        }
+
+        let mut reachable = BTreeSet::new();
+        for task_name in task_names {
+            let start = graph
+                .nodes()
+                .find(|node| *node == task_name)
+                .ok_or_else(|| crate::Error::NoSuchTask {
+                    task_name: task_name.to_string(),
+                })?;
+
+            let mut dfs = Dfs::new(&graph, start);
+            while let Some(node) = dfs.next(&graph) {
+                reachable.insert(node);
+            }
+        }
+
+        let mut subgraph = DiGraphMap::new();
+        for node in graph.nodes().filter(|node| reachable.contains(node)) {
+            subgraph.add_node(node);
+        }
+
+        for (from, to, ()) in graph.all_edges() {
+            if reachable.contains(from) {
+                subgraph.add_edge(from, to, ());
+            }
+        }
+
+        Ok(subgraph)
+    }
+}
+
+/// Produces the lines of a node label: the task name, followed by its wrapped description.
+fn label_lines<'a>(name: &'a str, config: &'a NurFile) -> Vec<std::borrow::Cow<'a, str>> {
+    let mut lines = vec![std::borrow::Cow::Borrowed(name)];
+    match config.tasks.get(name) {
+        Some(task) if !task.description.is_empty() => {
+            lines.extend(textwrap::wrap(&task.description, DESCRIPTION_WIDTH))
+        }
+        Some(_) => {}
+        None => lines.push("(no such task)".into()),
+    }
+
+    lines
+}
+
+fn sorted_nodes<'a>(graph: &DiGraphMap<&'a str, ()>) -> Vec<&'a str> {
+    let mut nodes: Vec<&str> = graph.nodes().collect();
+    nodes.sort();
+    nodes
+}
+
+fn sorted_edges<'a>(graph: &DiGraphMap<&'a str, ()>) -> Vec<(&'a str, &'a str)> {
+    let mut edges: Vec<(&str, &str)> = graph.all_edges().map(|(from, to, ())| (from, to)).collect();
+    edges.sort();
+    edges
+}
+
+fn render_dot(
+    graph: &DiGraphMap<&str, ()>,
+    config: &NurFile,
+    cycle: &BTreeSet<(&str, &str)>,
+) -> String {
+    fn escape(s: &str) -> String {
+        s.replace('\\', "\\\\").replace('"', "\\\"")
+    }
+
+    let in_cycle = |node: &str| cycle.iter().any(|(from, _)| *from == node);
+
+    let mut out = String::new();
+    out.push_str("digraph nur {\n");
+    out.push_str("    // edges point from each task to the tasks it depends upon\n");
+    out.push_str("    node [shape=box];\n");
+
+    for node in sorted_nodes(graph) {
+        let label: Vec<String> = label_lines(node, config)
+            .iter()
+            .map(|line| escape(line))
+            .collect();
+
+        let mut attrs = format!("label=\"{}\"", label.join("\\n"));
+        if !config.tasks.contains_key(node) {
+            attrs.push_str(", style=dashed");
+        }
+
+        if in_cycle(node) {
+            attrs.push_str(", color=red");
+        }
+
+        out.push_str(&format!("    \"{}\" [{attrs}];\n", escape(node)));
+    }
+
+    for (from, to) in sorted_edges(graph) {
+        let attrs = if cycle.contains(&(from, to)) {
+            " [color=red, penwidth=2]"
+        } else {
+            ""
+        };
+
+        out.push_str(&format!(
+            "    \"{}\" -> \"{}\"{attrs};\n",
+            escape(from),
+            escape(to)
+        ));
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+fn render_mermaid(
+    graph: &DiGraphMap<&str, ()>,
+    config: &NurFile,
+    cycle: &BTreeSet<(&str, &str)>,
+) -> String {
+    fn escape(s: &str) -> String {
+        s.replace('&', "#amp;")
+            .replace('"', "#quot;")
+            .replace('<', "#lt;")
+            .replace('>', "#gt;")
+    }
+
+    // Mermaid node IDs are restricted, so refer to nodes by index
+    let ids = sorted_nodes(graph);
+    let id = |node: &str| {
+        let ix = ids
+            .iter()
+            .position(|n| *n == node)
+            .expect("node is in graph");
+        format!("n{ix}")
+    };
+
+    let mut out = String::new();
+    out.push_str("flowchart TD\n");
+    out.push_str("    %% edges point from each task to the tasks it depends upon\n");
+
+    for &node in &ids {
+        let label: Vec<String> = label_lines(node, config)
+            .iter()
+            .map(|line| escape(line))
+            .collect();
+
+        out.push_str(&format!("    {}[\"{}\"]\n", id(node), label.join("<br>")));
+    }
+
+    let mut cycle_links = Vec::new();
+    for (ix, (from, to)) in sorted_edges(graph).into_iter().enumerate() {
+        if cycle.contains(&(from, to)) {
+            cycle_links.push(ix.to_string());
+        }
+
+        out.push_str(&format!("    {} --> {}\n", id(from), id(to)));
+    }
+
+    for &node in &ids {
+        if !config.tasks.contains_key(node) {
+            out.push_str(&format!("    style {} stroke-dasharray: 5 5\n", id(node)));
+        }
+
+        if cycle.iter().any(|(from, _)| *from == node) {
+            out.push_str(&format!("    style {} stroke:red\n", id(node)));
+        }
+    }
+
+    if !cycle_links.is_empty() {
+        out.push_str(&format!(
+            "    linkStyle {} stroke:red,stroke-width:2px\n",
+            cycle_links.join(",")
+        ));
+    }
+
+    out
+}
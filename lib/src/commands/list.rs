@@ -1,28 +1,220 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+    path::Path,
+};
+
 use miette::IntoDiagnostic;
 use owo_colors::OwoColorize;
+use serde::Serialize;
+
+use crate::{
+    commands::Format,
+    nurfile::{NurFile, NurTask},
+    output::ColorChoice,
+};
 
 pub struct List {
     pub nur_file: Option<std::path::PathBuf>,
+    /// If set, only tasks whose names match are listed. `*` matches any text
+    /// and `?` any character; a pattern without either matches names containing it.
+    pub pattern: Option<String>,
+    pub format: Format,
+    /// Shows the tasks that each task depends upon, as a tree.
+    pub tree: bool,
+    pub color: ColorChoice,
 }
 
 impl crate::commands::Command for List {
     fn run(&self, ctx: crate::commands::Context) -> miette::Result<()> {
-        let wrap_opts = textwrap::Options::with_termwidth()
-            .initial_indent("  - ")
-            .subsequent_indent("    ");
-
-        let (_, config) = crate::nurfile::load_config(&ctx.cwd, self.nur_file.as_deref())?;
+        let (path, config) = crate::nurfile::load_config(&ctx.cwd, self.nur_file.as_deref())?;
 
-        let name_style = owo_colors::Style::new().bold();
+        let pattern = self.pattern.as_deref().map(pattern_regex);
 
         // tasks are already sorted by name by virtue of being in a BTreeMap
-        for (name, task) in config.tasks {
-            writeln!(ctx.stdout, "{}", name.style(name_style)).into_diagnostic()?;
-            for line in textwrap::wrap(&task.description, &wrap_opts) {
-                writeln!(ctx.stdout, "{line}").into_diagnostic()?;
+        let tasks: BTreeMap<&str, &NurTask> = config
+            .tasks
+            .iter()
+            .map(|(name, task)| (name.as_str(), task))
+            .filter(|(name, _)| pattern.as_ref().is_none_or(|p| p.is_match(name)))
+            .collect();
+
+        let name_style = if self.color.enabled() {
+            owo_colors::Style::new().bold()
+        } else {
+            owo_colors::Style::new()
+        };
+
+        match self.format {
+            Format::Json => write_json(ctx.stdout, &path, &config, &tasks),
+            Format::Text if self.tree => write_tree(ctx.stdout, &config, &tasks, name_style),
+            Format::Text => write_text(ctx.stdout, &tasks, name_style),
+        }
+        .into_diagnostic()
+    }
+}
+
+/// Converts a name pattern to a regular expression.
+fn pattern_regex(pattern: &str) -> regex::Regex {
+    let escaped = regex::escape(pattern);
+    let regex = if pattern.contains(['*', '?']) {
+        format!("^{}$", escaped.replace(r"\*", ".*").replace(r"\?", "."))
+    } else {
+        escaped
+    };
+
+    regex::Regex::new(&regex).expect("escaped patterns are valid")
+}
+
+fn write_text(
+    out: &mut dyn Write,
+    tasks: &BTreeMap<&str, &NurTask>,
+    name_style: owo_colors::Style,
+) -> std::io::Result<()> {
+    let wrap_opts = textwrap::Options::with_termwidth()
+        .initial_indent("  - ")
+        .subsequent_indent("    ");
+
+    for (name, task) in tasks {
+        writeln!(out, "{}", name.style(name_style))?;
+        for line in textwrap::wrap(&task.description, &wrap_opts) {
+            writeln!(out, "{line}")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes each task which is not a dependency of another listed task,
+/// followed by the tree of tasks it depends upon. Tasks which are only
+/// depended upon in a cycle are written afterwards, as they are never reached.
+fn write_tree(
+    out: &mut dyn Write,
+    config: &NurFile,
+    tasks: &BTreeMap<&str, &NurTask>,
+    name_style: owo_colors::Style,
+) -> std::io::Result<()> {
+    let dependencies: BTreeSet<&str> = tasks
+        .values()
+        .flat_map(|task| task.dependencies.iter().map(String::as_str))
+        .collect();
+
+    let mut tree = Tree {
+        out,
+        config,
+        expanded: BTreeSet::new(),
+    };
+
+    let (roots, rest): (Vec<&str>, Vec<&str>) =
+        tasks.keys().partition(|name| !dependencies.contains(*name));
+
+    for name in roots.into_iter().chain(rest) {
+        if tree.expanded.insert(name) {
+            writeln!(tree.out, "{}", name.style(name_style))?;
+            tree.write_dependencies(name, "", &mut vec![name])?;
+        }
+    }
+
+    Ok(())
+}
+
+struct Tree<'a, 'w> {
+    out: &'w mut dyn Write,
+    config: &'a NurFile,
+    /// Tasks whose dependencies have already been shown.
+    expanded: BTreeSet<&'a str>,
+}
+
+impl<'a> Tree<'a, '_> {
+    fn write_dependencies(
+        &mut self,
+        name: &str,
+        indent: &str,
+        path: &mut Vec<&'a str>,
+    ) -> std::io::Result<()> {
+        let Some(task) = self.config.tasks.get(name) else {
+            return Ok(());
+        };
+
+        for (ix, dependency) in task.dependencies.iter().enumerate() {
+            let last = ix + 1 == task.dependencies.len();
+            let (branch, continuation) = if last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+
+            let (dependency, note) = match self.config.tasks.get_key_value(dependency) {
+                None => (dependency.as_str(), " (missing)"),
+                Some((dependency, _)) if path.contains(&dependency.as_str()) => {
+                    (dependency.as_str(), " (cycle)")
+                }
+                // dependencies are shown only once, since they may be shared by many tasks
+                Some((dependency, _)) if !self.expanded.insert(dependency) => {
+                    (dependency.as_str(), " (*)")
+                }
+                Some((dependency, _)) => (dependency.as_str(), ""),
+            };
+
+            writeln!(self.out, "{indent}{branch}{dependency}{note}")?;
+            if note.is_empty() {
+                path.push(dependency);
+                self.write_dependencies(dependency, &format!("{indent}{continuation}"), path)?;
+                path.pop();
             }
         }
 
         Ok(())
     }
 }
+
+#[derive(Serialize)]
+struct Catalogue<'a> {
+    nurfile: &'a Path,
+    /// Environment variables set for every task.
+    env: Vec<&'a str>,
+    tasks: Vec<ListedTask<'a>>,
+}
+
+#[derive(Serialize)]
+struct ListedTask<'a> {
+    name: &'a str,
+    description: &'a str,
+    dependencies: &'a [String],
+    /// Environment variables set by the task or its commands.
+    env: BTreeSet<&'a str>,
+    file: &'a Path,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+}
+
+fn write_json(
+    out: &mut dyn Write,
+    path: &Path,
+    config: &NurFile,
+    tasks: &BTreeMap<&str, &NurTask>,
+) -> std::io::Result<()> {
+    let catalogue = Catalogue {
+        nurfile: path,
+        env: config.env.keys().map(String::as_str).collect(),
+        tasks: tasks
+            .iter()
+            .map(|(name, task)| ListedTask {
+                name,
+                description: &task.description,
+                dependencies: &task.dependencies,
+                env: task
+                    .env
+                    .keys()
+                    .chain(task.commands.iter().flat_map(|cmd| cmd.env.keys()))
+                    .map(String::as_str)
+                    .collect(),
+                file: path,
+                line: task.line,
+            })
+            .collect(),
+    };
+
+    serde_json::to_writer_pretty(&mut *out, &catalogue)?;
+    writeln!(out)
+}
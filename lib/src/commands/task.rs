@@ -1,5 +1,12 @@
+mod plan;
+#[cfg(target_os = "linux")]
+mod pty;
+
+use std::borrow::Cow;
 use std::collections::{BTreeMap, BTreeSet, VecDeque};
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::Path;
+use std::time::SystemTime;
 
 use futures::{future::Shared, FutureExt};
 use miette::IntoDiagnostic;
@@ -12,56 +19,160 @@ use tokio::{
 };
 use tokio_util::sync::CancellationToken;
 
+use crate::commands::Format;
+use crate::matcher::{Diagnostic, Matcher, Scanner, SourceDiagnostic};
 use crate::nurfile::NurFile;
+use crate::output::{
+    highlight::{Counts, Highlighter, Severity},
+    Output,
+};
 use crate::{
-    nurfile::{NurTask, OutputOptions},
-    Error, StatusMessage, TaskError, TaskResult, TaskStatus,
+    nurfile::{CiProvider, NurTask, OutputOptions, OutputStyle, PrefixStyle, Streams},
+    Error, Line, StatusMessage, TaskError, TaskResult, TaskStatus,
 };
 
+#[derive(Default)]
 pub struct Task {
     pub dry_run: bool,
+    /// How the plan is printed for a dry run.
+    pub format: Format,
     pub nur_file: Option<std::path::PathBuf>,
-    pub task_names: BTreeSet<String>,
+    /// Names of the tasks to run, in the order given.
+    /// The name [`THEN_KEYWORD`] separates tasks that must run one after another.
+    pub task_names: Vec<String>,
+    /// Makes each requested task depend upon the one requested before it.
+    pub serial: bool,
+    /// Extra arguments passed to the commands of the requested task.
+    pub args: Vec<String>,
     pub output_override: Option<OutputOptions>,
+    /// The CI provider being run under, as detected by [`CiProvider::detect`].
+    /// Its output format is used unless the Nurfile disables it.
+    pub ci: Option<CiProvider>,
+    /// Prints a summary after running, regardless of the Nurfile’s setting.
+    pub summary: bool,
+    /// If set, a JUnit XML report of the run is written to this path.
+    pub junit_report: Option<std::path::PathBuf>,
+    /// If set, the output of each task is written to a file in this directory,
+    /// regardless of the Nurfile’s setting.
+    pub log_dir: Option<std::path::PathBuf>,
+    pub color: crate::output::ColorChoice,
+    /// Runs the commands of every task on a pseudo-terminal,
+    /// regardless of the Nurfile’s setting.
+    pub pty: bool,
 }
 
 const DEFAULT_TASK_NAME: &str = "default";
 
+/// How often the output is ticked while waiting for tasks.
+const TICK_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Separates requested tasks that must run in sequence, as in `nur clean then build`.
+pub const THEN_KEYWORD: &str = "then";
+
 impl crate::commands::Command for Task {
     fn run(&self, ctx: crate::commands::Context) -> miette::Result<()> {
         let (path, config) = crate::nurfile::load_config(&ctx.cwd, self.nur_file.as_deref())?;
 
-        let execution_order = self.tasks_from_config(path, &config)?;
+        let Schedule {
+            graph,
+            requested,
+            execution_order,
+        } = self.tasks_from_config(&path, &config)?;
+        let args_target = self.args_target()?;
+        let args = args_target.map(|target| (target, self.args.as_slice()));
 
         if self.dry_run {
-            ctx.stdout
-                .write_all("Would run tasks in the following order:\n".as_bytes())
-                .into_diagnostic()?;
+            let plan = plan::Plan::new(
+                &path,
+                &ctx.cwd,
+                &graph,
+                &requested,
+                &execution_order,
+                &config.tasks,
+                args,
+            );
 
-            for task_name in execution_order {
-                let msg = format!("- {task_name}\n");
-                ctx.stdout.write_all(msg.as_bytes()).into_diagnostic()?;
+            match self.format {
+                Format::Text => plan.write_text(ctx.stdout),
+                Format::Json => plan.write_json(ctx.stdout),
             }
+            .into_diagnostic()?;
 
             ctx.stdout.flush().into_diagnostic()?;
             Ok(())
         } else {
+            let log_options = self.log_options(&ctx.cwd, &path, &config);
+            let output_options = self.output_options(&config);
             let mut output = crate::output::create(
                 ctx.stdout,
                 ctx.stderr,
-                self.output_override
-                    .as_ref()
-                    .unwrap_or(&config.options.output),
+                &output_options,
                 &execution_order,
+                self.color,
+                log_options
+                    .as_ref()
+                    .map(|options| options.directory.as_path()),
+                &execution_order
+                    .iter()
+                    .map(|name| {
+                        config
+                            .tasks
+                            .get(*name)
+                            .map(|task| task.output)
+                            .unwrap_or_default()
+                    })
+                    .collect::<Vec<_>>(),
             );
 
+            let mut summary = (self.summary || config.options.summary).then(|| {
+                crate::output::Summary::new(
+                    execution_order
+                        .iter()
+                        .map(|name| name.to_string())
+                        .collect(),
+                )
+            });
+
+            let mut junit = self.junit_report.as_ref().map(|_| {
+                crate::output::JUnit::new(
+                    path.file_name()
+                        .unwrap_or(path.as_os_str())
+                        .to_string_lossy()
+                        .into_owned(),
+                    execution_order
+                        .iter()
+                        .map(|name| name.to_string())
+                        .collect(),
+                )
+            });
+
+            let mut logs = log_options
+                .map(|options| {
+                    crate::output::Logs::new(
+                        &options.directory,
+                        options.timestamps,
+                        &execution_order,
+                    )
+                    .map_err(|source| Error::LogDirectoryError {
+                        path: options.directory,
+                        source,
+                    })
+                })
+                .transpose()?;
+
             let (tx, mut rx) = mpsc::channel::<crate::StatusMessage>(100);
             let local_ctx = LocalContext {
                 cwd: ctx.cwd.clone(),
                 tx,
+                cancellation: CancellationToken::new(),
+                kill: CancellationToken::new(),
+                matchers: config.options.matchers.clone().into(),
+                highlighter: output_options.highlight.clone().into(),
+                pty: self.pty,
+                reserved_columns: reserved_columns(&output_options, &execution_order),
             };
 
-            let task_results = std::thread::scope(|s| {
+            let (task_results, stopped) = std::thread::scope(|s| {
                 // one thread for all tasks to run on
                 let result = s.spawn(|| {
                     let tokio_rt = tokio::runtime::Builder::new_current_thread()
@@ -70,22 +181,110 @@ impl crate::commands::Command for Task {
                         .unwrap();
 
                     tokio_rt
-                        .block_on(run_tasks(local_ctx, execution_order, &config.tasks))
+                        .block_on(run_tasks(
+                            local_ctx,
+                            &graph,
+                            execution_order,
+                            &config.tasks,
+                            args,
+                        ))
                         .unwrap()
                 });
 
-                // do I/O on main thread
-                while let Some(msg) = rx.blocking_recv() {
+                // do I/O on main thread, ticking the output while waiting
+                let tick_rt = tokio::runtime::Builder::new_current_thread()
+                    .enable_time()
+                    .build()
+                    .unwrap();
+
+                let mut ticker = crate::output::Ticker::new(TICK_INTERVAL);
+                loop {
+                    let received = tick_rt.block_on(async {
+                        tokio::time::timeout(ticker.remaining(), rx.recv()).await
+                    });
+
+                    if ticker.due() {
+                        output.tick();
+                    }
+
+                    let msg = match received {
+                        Ok(Some(msg)) => msg,
+                        Ok(None) => break,
+                        Err(_elapsed) => continue,
+                    };
+
+                    let reports: [Option<&mut dyn Output<StatusMessage>>; 3] = [
+                        summary.as_mut().map(|x| x as _),
+                        junit.as_mut().map(|x| x as _),
+                        logs.as_mut().map(|x| x as _),
+                    ];
+
+                    for report in reports.into_iter().flatten() {
+                        report.handle(msg.clone());
+                    }
+
                     output.handle(msg);
                 }
 
                 result.join().unwrap()
             });
 
-            let failures =
-                Vec::from_iter(task_results.into_iter().filter_map(|result| result.err()));
+            drop(output);
+            if let Some(summary) = summary {
+                // the JSON event stream on stdout must stay machine-readable
+                let out = match output_options.style {
+                    OutputStyle::Json => &mut *ctx.stderr,
+                    _ => &mut *ctx.stdout,
+                };
 
-            if failures.len() == 1 {
+                summary.write(out).into_diagnostic()?;
+            }
+
+            if let (Some(junit), Some(report_path)) = (junit, &self.junit_report) {
+                let report_path = ctx.cwd.join(report_path);
+                std::fs::File::create(&report_path)
+                    .map(std::io::BufWriter::new)
+                    .and_then(|mut file| {
+                        junit.write(&mut file)?;
+                        file.flush()
+                    })
+                    .map_err(|source| Error::ReportWriteError {
+                        path: report_path,
+                        source,
+                    })?;
+            }
+
+            let mut failures = Vec::from_iter(
+                task_results
+                    .into_iter()
+                    .filter_map(|result| result.err())
+                    .map(|failure| match (failure, &logs) {
+                        (Error::TaskFailed(mut failure), Some(logs)) if failure.help.is_none() => {
+                            failure.help = logs.path(&failure.task_name).map(|path| {
+                                format!("the full output of the task is in {}", path.display())
+                            });
+
+                            Error::TaskFailed(failure)
+                        }
+                        (failure, _) => failure,
+                    }),
+            );
+
+            if let OutputStyle::Sections(CiProvider::GitHubActions) = output_options.style {
+                crate::output::github::write_annotations(
+                    ctx.stdout, &ctx.cwd, &path, &config, &failures,
+                )
+                .into_diagnostic()?;
+            }
+
+            if let Some((path, source)) = logs.as_mut().and_then(|logs| logs.take_error()) {
+                failures.push(Error::LogWriteError { path, source });
+            }
+
+            if let Some(stopped) = stopped {
+                // failures are most likely due to the cancellation
+                Err(stopped.into())
+            } else if failures.len() == 1 {
                 Err(failures.into_iter().next().unwrap().into())
             } else if failures.is_empty() {
                 Ok(())
@@ -96,26 +295,44 @@ impl crate::commands::Command for Task {
     }
 }
 
+/// The tasks to be run and the dependencies between them.
+struct Schedule<'a> {
+    graph: DiGraphMap<&'a str, ()>,
+    requested: Vec<&'a str>,
+    execution_order: Vec<&'a str>,
+}
+
 impl Task {
     fn tasks_from_config<'a>(
         &'a self,
-        path: PathBuf,
+        path: &Path,
         config: &'a NurFile,
-    ) -> crate::Result<Vec<&'a str>> {
+    ) -> crate::Result<Schedule<'a>> {
+        let stages = self.requested_stages();
+
         let graph = {
-            let mut graph: DiGraphMap<&str, ()> = DiGraphMap::new();
-            for (name, data) in &config.tasks {
-                graph.add_node(name);
-                for dep in &data.dependencies {
-                    graph.add_edge(name, dep, ());
+            let mut graph = config.dependency_graph();
+
+            // tasks requested in sequence depend upon all tasks in the previous stage,
+            // unless the earlier task already depends upon the later one, which
+            // must then run first anyway: this is not a cycle in the Nurfile
+            for pair in stages.windows(2) {
+                for &later in &pair[1] {
+                    for &earlier in &pair[0] {
+                        if later != earlier
+                            && !petgraph::algo::has_path_connecting(&graph, earlier, later, None)
+                        {
+                            graph.add_edge(later, earlier, ());
+                        }
+                    }
                 }
             }
+
             graph
         };
 
         // validate no cycles in graph
-        let initial_task = config.tasks.keys().next().map(|s| s.as_str());
-        let cycle = find_cycle(&graph, initial_task);
+        let cycle = find_cycle(&graph, None);
         if !cycle.is_empty() {
             let task_names = cycle
                 .into_iter()
@@ -123,27 +340,174 @@ impl Task {
                 .collect();
 
             let cycle = crate::Cycle { path: task_names };
-            return Err(crate::Error::TaskCycle { path, cycle });
+            return Err(crate::Error::TaskCycle {
+                path: path.to_owned(),
+                cycle,
+            });
+        }
+
+        let requested: Vec<&str> = stages.into_iter().flatten().collect();
+        let execution_order = get_execution_order(&graph, requested.clone());
+
+        if let Some(missing) = execution_order
+            .iter()
+            .find(|task_name| !config.tasks.contains_key(**task_name))
+        {
+            return Err(crate::Error::NoSuchTask {
+                task_name: missing.to_string(),
+            });
         }
 
-        Ok(get_execution_order(graph, &self.task_names))
+        Ok(Schedule {
+            graph,
+            requested,
+            execution_order,
+        })
     }
-}
 
-fn get_execution_order<'a>(
-    graph: DiGraphMap<&'a str, ()>,
-    tasks: &'a BTreeSet<String>,
-) -> Vec<&'a str> {
-    let mut to_visit = {
-        if tasks.is_empty() {
-            VecDeque::from([DEFAULT_TASK_NAME])
+    /// Groups the requested tasks into stages, each of which must complete before the next starts.
+    fn requested_stages(&self) -> Vec<Vec<&str>> {
+        let mut stages = Vec::new();
+        let mut current = Vec::new();
+        for task_name in &self.task_names {
+            if task_name == THEN_KEYWORD {
+                if !current.is_empty() {
+                    stages.push(std::mem::take(&mut current));
+                }
+            } else if self.serial {
+                stages.push(vec![task_name.as_str()]);
+            } else {
+                current.push(task_name.as_str());
+            }
+        }
+
+        if !current.is_empty() {
+            stages.push(current);
+        }
+
+        if stages.is_empty() {
+            stages.push(vec![DEFAULT_TASK_NAME]);
+        }
+
+        stages
+    }
+
+    /// Determines the output style, which is chosen by (in order of preference):
+    /// the override, the CI provider being run under, or the Nurfile.
+    fn output_options<'a>(&'a self, config: &'a NurFile) -> Cow<'a, OutputOptions> {
+        // highlighting rules describe the tasks’ output rather than how it is shown,
+        // so they are kept whichever style is used
+        let highlight = || config.options.output.highlight.clone();
+        if let Some(output) = &self.output_override {
+            Cow::Owned(OutputOptions {
+                highlight: highlight(),
+                ..output.clone()
+            })
+        } else if let Some(provider) = config.options.ci.provider(self.ci) {
+            Cow::Owned(OutputOptions {
+                highlight: highlight(),
+                ..OutputOptions::ci(provider)
+            })
         } else {
-            VecDeque::from_iter(tasks.iter().map(|x| x.as_str()))
+            Cow::Borrowed(&config.options.output)
+        }
+    }
+
+    /// Determines where task logs are written, if anywhere.
+    fn log_options(
+        &self,
+        cwd: &Path,
+        nurfile_path: &Path,
+        config: &NurFile,
+    ) -> Option<crate::nurfile::LogOptions> {
+        let configured = config.options.logs.as_ref();
+        match &self.log_dir {
+            Some(directory) => Some(crate::nurfile::LogOptions {
+                directory: cwd.join(directory),
+                timestamps: configured.is_some_and(|logs| logs.timestamps),
+            }),
+            None => configured.map(|logs| crate::nurfile::LogOptions {
+                directory: nurfile_path.parent().unwrap_or(cwd).join(&logs.directory),
+                timestamps: logs.timestamps,
+            }),
+        }
+    }
+
+    /// Finds the task that extra arguments will be passed to, if there are any.
+    fn args_target(&self) -> crate::Result<Option<&str>> {
+        if self.args.is_empty() {
+            return Ok(None);
+        }
+
+        // tasks may be requested more than once, as in `nur a then b then a`
+        let mut seen = BTreeSet::new();
+        let task_names: Vec<&str> = self
+            .requested_stages()
+            .into_iter()
+            .flatten()
+            .filter(|task_name| seen.insert(*task_name))
+            .collect();
+
+        match task_names.as_slice() {
+            [task_name] => Ok(Some(task_name)),
+            _ => Err(Error::ArgumentsWithMultipleTasks {
+                task_names: task_names.into_iter().map(|x| x.to_string()).collect(),
+            }),
+        }
+    }
+}
+
+/// Estimates how many columns the prefixes of output lines take,
+/// which is the longest task name and a separator.
+fn reserved_columns(options: &OutputOptions, execution_order: &[&str]) -> u16 {
+    let separator = match &options.style {
+        OutputStyle::Grouped { separator, .. } | OutputStyle::Streamed { separator, .. } => {
+            separator
         }
+        // other styles do not prefix lines
+        _ => return 0,
     };
 
+    let name = match options.prefix {
+        PrefixStyle::NoPrefix => 0,
+        PrefixStyle::Always | PrefixStyle::Aligned => execution_order
+            .iter()
+            .map(|name| name.chars().count())
+            .max()
+            .unwrap_or_default(),
+    };
+
+    (name + separator.chars().count())
+        .try_into()
+        .unwrap_or(u16::MAX)
+}
+
+/// Quotes arguments for display so that their boundaries are visible.
+fn shell_quote(args: &[String]) -> String {
+    let quoted: Vec<String> = args
+        .iter()
+        .map(|arg| {
+            let is_safe = !arg.is_empty()
+                && arg
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_.,:/=+@%".contains(c));
+
+            if is_safe {
+                arg.clone()
+            } else {
+                format!("'{}'", arg.replace('\'', "'\\''"))
+            }
+        })
+        .collect();
+
+    quoted.join(" ")
+}
+
+fn get_execution_order<'a>(graph: &DiGraphMap<&'a str, ()>, tasks: Vec<&'a str>) -> Vec<&'a str> {
+    let mut to_visit = VecDeque::from(tasks);
+
     let mut visitor = petgraph::visit::DfsPostOrder::new(
-        &graph,
+        graph,
         to_visit
             .pop_front()
             .expect("always at least one in to_visit"),
@@ -152,14 +516,14 @@ fn get_execution_order<'a>(
     // build the execution order for the graph
     // this iterates from the first to_visit member
     let mut run_order = Vec::new();
-    while let Some(nx) = visitor.next(&graph) {
+    while let Some(nx) = visitor.next(graph) {
         run_order.push(nx);
     }
 
     // now visit the rest of the to_visit members
     while let Some(start) = to_visit.pop_front() {
         visitor.move_to(start);
-        while let Some(nx) = visitor.next(&graph) {
+        while let Some(nx) = visitor.next(graph) {
             run_order.push(nx);
         }
     }
@@ -171,14 +535,26 @@ fn get_execution_order<'a>(
 struct LocalContext {
     cwd: std::path::PathBuf,
     tx: mpsc::Sender<crate::StatusMessage>,
+    cancellation: CancellationToken,
+    /// Kills running commands, without waiting for them to stop.
+    kill: CancellationToken,
+    matchers: std::sync::Arc<[Matcher]>,
+    /// Rules which count warnings and errors in the output of tasks.
+    highlighter: std::sync::Arc<Highlighter>,
+    /// Runs every task on a terminal, whatever its own setting.
+    pty: bool,
+    /// Columns of the terminal taken by the prefixes of output lines,
+    /// which are not available to commands run on a terminal.
+    reserved_columns: u16,
 }
 
 async fn run_tasks(
     ctx: LocalContext,
+    graph: &DiGraphMap<&str, ()>,
     run_order: Vec<&str>,
     tasks: &BTreeMap<String, NurTask>,
-) -> miette::Result<Vec<crate::Result<TaskResult>>> {
-    let cancellation = CancellationToken::new();
+    args: Option<(&str, &[String])>,
+) -> miette::Result<(Vec<crate::Result<TaskResult>>, Option<Error>)> {
     let mut spawned = Vec::with_capacity(run_order.len());
     {
         let mut so_far: BTreeMap<&str, Shared<oneshot::Receiver<()>>> = BTreeMap::new();
@@ -189,15 +565,15 @@ async fn run_tasks(
                     task_name: task_name.to_string(),
                 })?;
 
-            // get receivers for all dependencies:
+            // get receivers for all dependencies,
+            // including any added by sequencing the requested tasks:
             let mut await_on = Vec::with_capacity(task.dependencies.len());
-            for dependency in &task.dependencies {
-                let recvr =
-                    so_far
-                        .get(dependency.as_str())
-                        .ok_or_else(|| crate::Error::NoSuchTask {
-                            task_name: dependency.to_string(),
-                        })?;
+            for dependency in graph.neighbors(task_name) {
+                let recvr = so_far
+                    .get(dependency)
+                    .ok_or_else(|| crate::Error::NoSuchTask {
+                        task_name: dependency.to_string(),
+                    })?;
 
                 await_on.push(recvr.clone());
             }
@@ -205,29 +581,95 @@ async fn run_tasks(
             let (sender, receiver) = oneshot::channel();
             so_far.insert(task_name, receiver.shared());
 
+            let task_args = match args {
+                Some((target, args)) if target == task_name => args,
+                _ => &[],
+            };
+
             spawned.push(run_task(
                 ctx.clone(),
-                cancellation.clone(),
                 await_on,
                 task_id,
                 task_name,
                 task,
+                task_args,
                 sender,
             ));
         }
     }
 
-    Ok(futures::future::join_all(spawned).await)
+    let mut signals = StopSignals::new();
+    let mut results = std::pin::pin!(futures::future::join_all(spawned));
+    let signal = tokio::select! {
+        results = &mut results => return Ok((results, None)),
+        signal = signals.recv() => signal,
+    };
+
+    // let running tasks stop, and skip the rest
+    ctx.cancellation.cancel();
+
+    // tasks which cannot be cancelled are waited for, unless asked again
+    let signal = tokio::select! {
+        results = &mut results => return Ok((results, Some(Error::Cancelled { signal }))),
+        signal = signals.recv() => signal,
+    };
+
+    ctx.kill.cancel();
+    Ok((results.await, Some(Error::Killed { signal })))
+}
+
+/// Listens for signals asking nur to stop.
+///
+/// Once listening, the signals no longer stop nur by default, so the
+/// listeners are kept for as long as nur needs to respond to them.
+struct StopSignals {
+    #[cfg(unix)]
+    listeners: Option<(tokio::signal::unix::Signal, tokio::signal::unix::Signal)>,
+}
+
+impl StopSignals {
+    fn new() -> Self {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            StopSignals {
+                listeners: signal(SignalKind::interrupt())
+                    .ok()
+                    .zip(signal(SignalKind::terminate()).ok()),
+            }
+        }
+
+        #[cfg(not(unix))]
+        StopSignals {}
+    }
+
+    /// Waits for the next signal, returning its number.
+    async fn recv(&mut self) -> i32 {
+        #[cfg(unix)]
+        if let Some((interrupt, terminate)) = &mut self.listeners {
+            return tokio::select! {
+                _ = interrupt.recv() => 2 /* SIGINT */,
+                _ = terminate.recv() => 15 /* SIGTERM */,
+            };
+        }
+
+        #[cfg(not(unix))]
+        if tokio::signal::ctrl_c().await.is_ok() {
+            return 2 /* SIGINT */;
+        }
+
+        std::future::pending().await
+    }
 }
 
 /// Executes a single task and emits start/stop events.
 async fn run_task(
     ctx: LocalContext,
-    cancellation: CancellationToken,
     await_on: Vec<Shared<oneshot::Receiver<()>>>,
     task_id: usize,
     task_name: &str,
     task: &NurTask,
+    args: &[String],
     done: oneshot::Sender<()>,
 ) -> miette::Result<TaskResult, crate::Error> {
     // if upstream task failed it will not trigger its "done" sender,
@@ -236,7 +678,15 @@ async fn run_task(
         // don’t report this as an error; task cancelled
         let result = TaskResult::Skipped;
         ctx.tx
-            .send((task_id, TaskStatus::Finished { result: Ok(result) }))
+            .send((
+                task_id,
+                TaskStatus::Finished {
+                    result: Ok(result),
+                    time: SystemTime::now(),
+                    diagnostics: Vec::new(),
+                    counts: Counts::default(),
+                },
+            ))
             .await
             .map_err(crate::internal_error)?;
 
@@ -244,11 +694,17 @@ async fn run_task(
     }
 
     ctx.tx
-        .send((task_id, TaskStatus::Started {}))
+        .send((
+            task_id,
+            TaskStatus::Started {
+                time: SystemTime::now(),
+            },
+        ))
         .await
         .map_err(crate::internal_error)?;
 
-    let result = run_cmds(&ctx, task_id, task, &cancellation).await;
+    let mut findings = Findings::default();
+    let result = run_cmds(&ctx, task_id, task, args, &mut findings).await;
     if let Ok(TaskResult::RanToCompletion) = result {
         // trigger dependent tasks,
         // ignore failures from downstream tasks not existing
@@ -263,24 +719,56 @@ async fn run_task(
             task_id,
             TaskStatus::Finished {
                 result: result.clone(),
+                time: SystemTime::now(),
+                diagnostics: findings.diagnostics.clone(),
+                counts: findings.counts,
             },
         ))
         .await
         .map_err(crate::internal_error)?;
 
-    result.map_err(|task_error| crate::Error::TaskFailed {
-        task_name: task_name.to_string(),
-        task_error,
+    result.map_err(|task_error| {
+        crate::Error::TaskFailed(crate::TaskFailure {
+            task_name: task_name.to_string(),
+            task_error,
+            help: None,
+            // only errors are shown in the report, since they are what broke
+            diagnostics: findings
+                .diagnostics
+                .iter()
+                .filter(|d| d.severity == Severity::Error)
+                .map(|d| SourceDiagnostic::new(d, &ctx.cwd))
+                .collect(),
+        })
     })
 }
 
-/// Executes the commands for a single task.
+/// What was found in the output of a task.
+#[derive(Default)]
+struct Findings {
+    /// Problems found by the matchers.
+    diagnostics: Vec<Diagnostic>,
+    /// Lines of each severity, found by the highlighting rules.
+    counts: Counts,
+}
+
+impl Findings {
+    fn extend(&mut self, other: Findings) {
+        self.diagnostics.extend(other.diagnostics);
+        self.counts += other.counts;
+    }
+}
+
+/// Executes the commands for a single task,
+/// collecting what is found in their output.
 async fn run_cmds(
     ctx: &LocalContext,
     task_id: usize,
     task: &NurTask,
-    cancellation: &tokio_util::sync::CancellationToken,
+    args: &[String],
+    findings: &mut Findings,
 ) -> Result<TaskResult, TaskError> {
+    let cancellation = &ctx.cancellation;
     for cmd in &task.commands {
         // last-chance check before starting process
         if cancellation.is_cancelled() {
@@ -289,12 +777,21 @@ async fn run_cmds(
 
         let shell = "/bin/sh";
 
+        let (stdout, stderr, combined) =
+            output_pipes(ctx, task).map_err(|e| TaskError::ExecutableError {
+                executable: shell.to_string(),
+                kind: e.kind(),
+            })?;
+
         let mut wrapper = TokioCommandWrap::with_new(shell, |c| {
-            c.args(["-c", &cmd.sh])
+            // extra arguments become the positional parameters of the script,
+            // after $0 which is set to the shell as it would be normally
+            c.args(["-c", &cmd.sh, shell])
+                .args(args)
                 .current_dir(&ctx.cwd)
                 .stdin(std::process::Stdio::null())
-                .stdout(std::process::Stdio::piped())
-                .stderr(std::process::Stdio::piped())
+                .stdout(stdout)
+                .stderr(stderr)
                 .envs(&task.env) // task environment is overridden by cmd
                 .envs(&cmd.env);
         });
@@ -312,12 +809,30 @@ async fn run_cmds(
             kind: e.kind(),
         })?;
 
-        let stdout = child.inner_mut().stdout.take().expect("no stdout handle");
-        let stderr = child.inner_mut().stderr.take().expect("no stderr handle");
+        // the command holds the write end of a shared pipe or terminal,
+        // which must be closed for the reader to see the end of the output
+        drop(wrapper);
 
-        let ((), (), status) = tokio::join!(
-            spawn_reader(stdout, ctx.tx.clone(), task_id, TaskStatus::StdOut),
-            spawn_reader(stderr, ctx.tx.clone(), task_id, TaskStatus::StdErr),
+        let stdout = child.inner_mut().stdout.take();
+        let stderr = child.inner_mut().stderr.take();
+
+        let (stdout_findings, stderr_findings, combined_findings, status) = tokio::join!(
+            spawn_optional_reader(stdout, ctx, task_id, TaskStatus::StdOut),
+            spawn_optional_reader(stderr, ctx, task_id, TaskStatus::StdErr),
+            async {
+                let Some(CombinedOutput {
+                    reader,
+                    follow_size,
+                }) = combined
+                else {
+                    return Findings::default();
+                };
+
+                tokio::select! {
+                    findings = spawn_reader(reader, ctx, task_id, TaskStatus::Combined) => findings,
+                    () = follow_size => Findings::default(),
+                }
+            },
             async move {
                 tokio::select! {
                     () = cancellation.cancelled(), if task.cancellable => {
@@ -327,16 +842,32 @@ async fn run_cmds(
                                 return Some(Box::into_pin(child.wait()).await);
                             }
                         }
-                        _ = Box::into_pin(child.wait()).await;
-                        None
                     }
+                    () = ctx.kill.cancelled() => {}
                     result = Box::into_pin(child.wait()) => {
-                        Some(result)
+                        return Some(result);
                     }
                 }
+
+                // the command is waited for, unless nur is asked to stop again
+                let killed = tokio::select! {
+                    _ = Box::into_pin(child.wait()) => false,
+                    () = ctx.kill.cancelled() => true,
+                };
+
+                if killed {
+                    // the whole process group is killed, so nothing is left behind
+                    _ = Box::into_pin(child.kill()).await;
+                }
+
+                None
             },
         );
 
+        findings.extend(stdout_findings);
+        findings.extend(stderr_findings);
+        findings.extend(combined_findings);
+
         if let Some(status) = status {
             let exit_status = status.map_err(|e| TaskError::ExecutableWaitFailure {
                 executable: shell.to_string(),
@@ -348,6 +879,7 @@ async fn run_cmds(
                 return Err(TaskError::Failed {
                     command: cmd.sh.clone(),
                     exit_status,
+                    line: cmd.line,
                 });
             }
         } else {
@@ -359,19 +891,206 @@ async fn run_cmds(
     Ok(TaskResult::RanToCompletion)
 }
 
+/// The output of a command whose stdout and stderr share a single pipe or terminal.
+struct CombinedOutput {
+    reader: std::pin::Pin<Box<dyn AsyncRead + Send>>,
+    /// Keeps the size of the terminal in step with nur’s own. This never finishes.
+    follow_size: std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>>,
+}
+
+/// Creates the stdout and stderr for a command, and the reader
+/// for its output if both streams are to share a single pipe or terminal.
+///
+/// Terminals are only supported on Linux, and shared pipes on Unix;
+/// elsewhere, the streams are read separately.
+fn output_pipes(
+    ctx: &LocalContext,
+    task: &NurTask,
+) -> std::io::Result<(
+    std::process::Stdio,
+    std::process::Stdio,
+    Option<CombinedOutput>,
+)> {
+    use std::process::Stdio;
+
+    #[cfg(target_os = "linux")]
+    if task.pty || ctx.pty {
+        let pty::Pty {
+            stdout,
+            stderr,
+            reader,
+            resizer,
+        } = pty::Pty::open(ctx.reserved_columns)?;
+
+        let combined = CombinedOutput {
+            reader: Box::pin(reader),
+            follow_size: Box::pin(async move { resizer.follow().await }),
+        };
+
+        return Ok((stdout, stderr, Some(combined)));
+    }
+
+    #[cfg(unix)]
+    if task.streams == Streams::Combined {
+        let (reader, writer) = std::io::pipe()?;
+        let combined = CombinedOutput {
+            reader: Box::pin(tokio::net::unix::pipe::Receiver::from_owned_fd(
+                reader.into(),
+            )?),
+            follow_size: Box::pin(std::future::pending()),
+        };
+
+        return Ok((writer.try_clone()?.into(), writer.into(), Some(combined)));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    let _ = ctx;
+
+    Ok((Stdio::piped(), Stdio::piped(), None))
+}
+
+async fn spawn_optional_reader<R>(
+    from: Option<R>,
+    ctx: &LocalContext,
+    task_id: usize,
+    f: impl Fn(Line) -> TaskStatus,
+) -> Findings
+where
+    R: AsyncRead + Send + 'static,
+    BufReader<R>: Unpin,
+{
+    match from {
+        Some(from) => spawn_reader(from, ctx, task_id, f).await,
+        None => Findings::default(),
+    }
+}
+
 async fn spawn_reader<R>(
     from: R,
-    into: mpsc::Sender<StatusMessage>,
+    ctx: &LocalContext,
     task_id: usize,
-    f: impl Fn(String) -> TaskStatus,
-) where
+    f: impl Fn(Line) -> TaskStatus,
+) -> Findings
+where
     R: AsyncRead + Send + 'static,
     BufReader<R>: Unpin,
 {
-    let mut reader = BufReader::new(from).lines();
-    while let Ok(Some(line)) = reader.next_line().await {
-        if (into.send((task_id, f(line))).await).is_err() {
-            break;
+    let into = &ctx.tx;
+    let mut scanner = Scanner::new(&ctx.matchers);
+    let mut counts = Counts::default();
+    let mut scan = |line: &Line| {
+        scanner.scan(line);
+        if let Some(severity) = ctx.highlighter.severity(line) {
+            counts.add(severity);
+        }
+    };
+
+    let mut reader = BufReader::new(from);
+    let mut splitter = LineSplitter::default();
+    loop {
+        // output is read as bytes, since tasks may write anything
+        let pieces = match reader.fill_buf().await {
+            Ok([]) | Err(_) => break,
+            Ok(chunk) => {
+                let len = chunk.len();
+                let pieces = splitter.push(chunk);
+                reader.consume(len);
+                pieces
+            }
+        };
+
+        for piece in pieces {
+            let status = match piece {
+                Piece::Line(bytes) => {
+                    let line = Line::from_bytes(bytes);
+                    scan(&line);
+                    f(line)
+                }
+                Piece::Progress(bytes) => TaskStatus::Progress(Line::from_bytes(bytes)),
+            };
+
+            if (into.send((task_id, status)).await).is_err() {
+                return Findings {
+                    diagnostics: scanner.finish(),
+                    counts,
+                };
+            }
+        }
+    }
+
+    // a final line without a newline is still a line
+    if let Some(bytes) = splitter.finish() {
+        let line = Line::from_bytes(bytes);
+        scan(&line);
+        _ = into.send((task_id, f(line))).await;
+    }
+
+    Findings {
+        diagnostics: scanner.finish(),
+        counts,
+    }
+}
+
+/// Splits output into lines. Lines which are overwritten using carriage returns,
+/// such as progress bars, produce their intermediate states as progress,
+/// and only their final state as a line.
+#[derive(Default)]
+struct LineSplitter {
+    /// The text written since the last line ending or carriage return.
+    current: Vec<u8>,
+    /// The text before the last carriage return, which `current` overwrites.
+    overwritten: Vec<u8>,
+    /// Whether the last byte was a carriage return,
+    /// which may be the start of a line ending.
+    after_cr: bool,
+}
+
+enum Piece {
+    Line(Vec<u8>),
+    Progress(Vec<u8>),
+}
+
+impl LineSplitter {
+    fn push(&mut self, bytes: &[u8]) -> Vec<Piece> {
+        let mut pieces = Vec::new();
+        for &byte in bytes {
+            if self.after_cr && byte != b'\r' {
+                self.after_cr = false;
+                // a carriage return which does not end the line overwrites it
+                if byte != b'\n' && !self.overwritten.is_empty() {
+                    pieces.push(Piece::Progress(self.overwritten.clone()));
+                }
+            }
+
+            match byte {
+                b'\n' => pieces.push(Piece::Line(self.take_line())),
+                b'\r' => {
+                    if !self.current.is_empty() {
+                        self.overwritten = std::mem::take(&mut self.current);
+                    }
+
+                    self.after_cr = true;
+                }
+                _ => self.current.push(byte),
+            }
+        }
+
+        pieces
+    }
+
+    /// Takes the final state of the line, which is the last text written to it.
+    fn take_line(&mut self) -> Vec<u8> {
+        let overwritten = std::mem::take(&mut self.overwritten);
+        let current = std::mem::take(&mut self.current);
+        if current.is_empty() {
+            overwritten
+        } else {
+            current
         }
     }
+
+    /// Takes any line which was not ended before the output finished.
+    fn finish(mut self) -> Option<Vec<u8>> {
+        Some(self.take_line()).filter(|line| !line.is_empty())
+    }
 }
@@ -0,0 +1,222 @@
+use std::{collections::BTreeMap, io::Write, path::Path};
+
+use petgraph::{graphmap::DiGraphMap, Direction};
+use serde::Serialize;
+
+use crate::nurfile::{NurCommand, NurTask};
+
+/// Everything that would happen when running a set of tasks, for `--dry-run`.
+#[derive(Serialize)]
+pub struct Plan<'a> {
+    nurfile: &'a Path,
+    working_directory: &'a Path,
+    stages: Vec<Vec<PlannedTask<'a>>>,
+}
+
+#[derive(Serialize)]
+struct PlannedTask<'a> {
+    name: &'a str,
+    reason: Reason<'a>,
+    dependencies: Vec<&'a str>,
+    working_directory: &'a Path,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    arguments: &'a [String],
+    commands: Vec<PlannedCommand<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Reason<'a> {
+    Requested,
+    DependencyOf { tasks: Vec<&'a str> },
+}
+
+#[derive(Serialize)]
+struct PlannedCommand<'a> {
+    sh: &'a str,
+    ignore_result: bool,
+    /// Environment variables which differ from those of the current process.
+    env: Vec<EnvChange<'a>>,
+}
+
+#[derive(Serialize)]
+struct EnvChange<'a> {
+    name: &'a str,
+    value: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    previous: Option<String>,
+}
+
+impl<'a> Plan<'a> {
+    pub fn new(
+        nurfile: &'a Path,
+        cwd: &'a Path,
+        graph: &DiGraphMap<&'a str, ()>,
+        requested: &[&str],
+        execution_order: &[&'a str],
+        tasks: &'a BTreeMap<String, NurTask>,
+        args: Option<(&str, &'a [String])>,
+    ) -> Self {
+        // each task goes in the stage after the latest of its dependencies;
+        // since the execution order is topological, dependencies are seen first
+        let mut stage_of: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut stages: Vec<Vec<PlannedTask>> = Vec::new();
+        for &name in execution_order {
+            let dependencies: Vec<&str> = graph.neighbors(name).collect();
+            let stage = dependencies
+                .iter()
+                .map(|dep| stage_of[dep] + 1)
+                .max()
+                .unwrap_or_default();
+
+            stage_of.insert(name, stage);
+
+            let reason = if requested.contains(&name) {
+                Reason::Requested
+            } else {
+                Reason::DependencyOf {
+                    tasks: graph
+                        .neighbors_directed(name, Direction::Incoming)
+                        .filter(|dependent| execution_order.contains(dependent))
+                        .collect(),
+                }
+            };
+
+            let task = &tasks[name];
+            let planned = PlannedTask {
+                name,
+                reason,
+                dependencies,
+                working_directory: cwd,
+                arguments: match args {
+                    Some((target, args)) if target == name => args,
+                    _ => &[],
+                },
+                commands: task
+                    .commands
+                    .iter()
+                    .map(|cmd| PlannedCommand::new(task, cmd))
+                    .collect(),
+            };
+
+            if stage == stages.len() {
+                stages.push(Vec::new());
+            }
+
+            stages[stage].push(planned);
+        }
+
+        Plan {
+            nurfile,
+            working_directory: cwd,
+            stages,
+        }
+    }
+
+    pub fn write_text(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        let task_count: usize = self.stages.iter().map(|s| s.len()).sum();
+        writeln!(out, "Nurfile: {:?}", self.nurfile)?;
+        writeln!(out, "Working directory: {:?}", self.working_directory)?;
+        writeln!(
+            out,
+            "Would run {} in {}:",
+            plural(task_count, "task"),
+            plural(self.stages.len(), "stage")
+        )?;
+
+        for (ix, stage) in self.stages.iter().enumerate() {
+            writeln!(out)?;
+            if stage.len() > 1 {
+                writeln!(out, "Stage {} ({} tasks in parallel):", ix + 1, stage.len())?;
+            } else {
+                writeln!(out, "Stage {}:", ix + 1)?;
+            }
+
+            for task in stage {
+                let reason = match &task.reason {
+                    Reason::Requested => "requested".to_string(),
+                    Reason::DependencyOf { tasks } => {
+                        let names: Vec<String> = tasks.iter().map(|t| format!("‘{t}’")).collect();
+                        format!("dependency of {}", names.join(", "))
+                    }
+                };
+
+                writeln!(out, "- ‘{}’ ({reason})", task.name)?;
+
+                if !task.arguments.is_empty() {
+                    writeln!(
+                        out,
+                        "    with arguments {} (available to commands as \"$@\")",
+                        super::shell_quote(task.arguments)
+                    )?;
+                }
+
+                for cmd in &task.commands {
+                    for change in &cmd.env {
+                        match &change.previous {
+                            Some(previous) => writeln!(
+                                out,
+                                "    {}={} (was {previous})",
+                                change.name, change.value
+                            )?,
+                            None => writeln!(out, "    {}={}", change.name, change.value)?,
+                        }
+                    }
+
+                    if cmd.ignore_result {
+                        writeln!(out, "    $ {} (result ignored)", cmd.sh)?;
+                    } else {
+                        writeln!(out, "    $ {}", cmd.sh)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn write_json(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        serde_json::to_writer_pretty(&mut *out, self)?;
+        writeln!(out)
+    }
+}
+
+impl<'a> PlannedCommand<'a> {
+    fn new(task: &'a NurTask, cmd: &'a NurCommand) -> Self {
+        // command environment overrides task environment, as when running
+        let mut env: BTreeMap<&str, &str> = BTreeMap::new();
+        for (name, value) in task.env.iter().chain(&cmd.env) {
+            env.insert(name, value);
+        }
+
+        let env = env
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let previous = std::env::var_os(name);
+                if previous.as_deref() == Some(value.as_ref()) {
+                    None
+                } else {
+                    Some(EnvChange {
+                        name,
+                        value,
+                        previous: previous.map(|p| p.to_string_lossy().into_owned()),
+                    })
+                }
+            })
+            .collect();
+
+        PlannedCommand {
+            sh: &cmd.sh,
+            ignore_result: cmd.ignore_result,
+            env,
+        }
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{count} {noun}")
+    } else {
+        format!("{count} {noun}s")
+    }
+}
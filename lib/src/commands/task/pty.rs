@@ -0,0 +1,86 @@
+//! Running commands on a pseudo-terminal, so that they behave as they would
+//! when run interactively: most tools only use colors and progress bars
+//! when their output is a terminal.
+
+use std::{
+    os::fd::{AsRawFd, OwnedFd},
+    process::Stdio,
+};
+
+use nix::pty::Winsize;
+
+/// The size used when nur’s own output is not a terminal.
+const DEFAULT_SIZE: (u16, u16) = (80, 24);
+
+/// The narrowest the terminal is made, however much space the prefixes take.
+const MIN_COLUMNS: u16 = 20;
+
+nix::ioctl_write_ptr_bad!(set_window_size, nix::libc::TIOCSWINSZ, Winsize);
+
+/// A terminal which a command’s stdout and stderr are both connected to.
+pub(super) struct Pty {
+    pub stdout: Stdio,
+    pub stderr: Stdio,
+    /// Reads the output of the command. Reading ends once the command,
+    /// and anything else with the terminal open, has exited.
+    pub reader: tokio::fs::File,
+    pub resizer: Resizer,
+}
+
+impl Pty {
+    /// Opens a terminal as wide as nur’s own, less the columns which are reserved for prefixes.
+    pub fn open(reserved_columns: u16) -> std::io::Result<Self> {
+        let result = nix::pty::openpty(&window_size(reserved_columns), None)?;
+
+        // duplicates are close-on-exec, unlike the originals,
+        // so that other commands being started do not inherit them
+        let master = result.master.try_clone()?;
+        let slave = result.slave.try_clone()?;
+
+        Ok(Pty {
+            stdout: slave.try_clone()?.into(),
+            stderr: slave.into(),
+            reader: tokio::fs::File::from_std(master.try_clone()?.into()),
+            resizer: Resizer {
+                master,
+                reserved_columns,
+            },
+        })
+    }
+}
+
+/// Keeps the size of a terminal in step with nur’s own.
+pub(super) struct Resizer {
+    master: OwnedFd,
+    reserved_columns: u16,
+}
+
+impl Resizer {
+    /// Resizes the terminal whenever nur’s terminal is resized. This never finishes.
+    pub async fn follow(&self) {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        if let Ok(mut changes) = signal(SignalKind::window_change()) {
+            while changes.recv().await.is_some() {
+                let size = window_size(self.reserved_columns);
+                // SAFETY: the descriptor is open, and the size is a valid `winsize`
+                _ = unsafe { set_window_size(self.master.as_raw_fd(), &size) };
+            }
+        }
+
+        std::future::pending().await
+    }
+}
+
+fn window_size(reserved_columns: u16) -> Winsize {
+    let (columns, rows) = terminal_size::terminal_size()
+        .map(|(width, height)| (width.0, height.0))
+        .unwrap_or(DEFAULT_SIZE);
+
+    Winsize {
+        ws_row: rows,
+        ws_col: columns.saturating_sub(reserved_columns).max(MIN_COLUMNS),
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    }
+}
@@ -0,0 +1,33 @@
+//! The exit codes of nur, so that scripts can tell failures apart.
+//!
+//! When exactly one task failed, nur exits with the exit code of the command
+//! which failed, or with 128 plus the signal number if the command was killed
+//! by a signal, as shells do. These may coincide with the codes below.
+//! Only the low byte of an exit code is kept, and if that is zero,
+//! as for an exit code of 256 on Windows, [`FAILURE`] is used instead.
+
+/// Something failed, such as more than one task, or a command which could not be started.
+pub const FAILURE: u8 = 1;
+
+/// The command line was invalid.
+pub const USAGE: u8 = 2;
+
+/// The Nurfile could not be found, read, or understood.
+pub const NURFILE: u8 = 3;
+
+/// The tasks depend upon each other in a cycle.
+pub const TASK_CYCLE: u8 = 4;
+
+/// A task which was requested, or depended upon, does not exist.
+pub const NO_SUCH_TASK: u8 = 5;
+
+/// Added to the number of the signal which stopped nur, or killed a command.
+pub const SIGNAL_BASE: u8 = 128;
+
+/// The exit code for a signal.
+pub fn signal(signal: i32) -> u8 {
+    u8::try_from(signal)
+        .ok()
+        .and_then(|signal| SIGNAL_BASE.checked_add(signal))
+        .unwrap_or(FAILURE)
+}
@@ -1,4 +1,6 @@
 pub mod commands;
+pub mod exit_code;
+pub mod matcher;
 pub mod nurfile;
 pub mod output;
 pub mod version;
@@ -54,6 +56,14 @@ pub enum Error {
         files: Vec<PathBuf>,
     },
 
+    #[error("Unable to read Nur file {path:?}")]
+    #[diagnostic(code(nur::nurfile_read_error))]
+    NurfileUnreadable {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
     #[error("Nur file {path:?} has a syntax error")]
     #[diagnostic(code(nur::syntax_error))]
     NurfileSyntaxError {
@@ -73,21 +83,130 @@ pub enum Error {
     )]
     NoSuchTask { task_name: String },
 
-    #[error("Task ‘{task_name}’ failed")]
-    #[diagnostic(code(nur::task_failed))]
-    TaskFailed {
-        task_name: String,
+    #[error("Extra arguments can only be passed when running a single task")]
+    #[diagnostic(
+        code(nur::arguments_with_multiple_tasks),
+        help("arguments after `--` are passed to the one task being run, but {} tasks were requested", task_names.len())
+    )]
+    ArgumentsWithMultipleTasks { task_names: Vec<String> },
+
+    #[error("Unable to create log directory {path:?}")]
+    #[diagnostic(code(nur::log_directory_error))]
+    LogDirectoryError {
+        path: PathBuf,
         #[source]
-        #[diagnostic_source]
-        task_error: TaskError,
+        source: std::io::Error,
+    },
+
+    #[error("Unable to write log {path:?}")]
+    #[diagnostic(
+        code(nur::log_write_error),
+        help("the tasks ran, but their output is missing from this log")
+    )]
+    LogWriteError {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Unable to write report to {path:?}")]
+    #[diagnostic(code(nur::report_write_error))]
+    ReportWriteError {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
     },
 
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    TaskFailed(TaskFailure),
+
     #[error("Multiple failures")]
     #[diagnostic(code(nur::multiple_failures))]
     Multiple {
         #[related]
         failures: Vec<Error>,
     },
+
+    #[error("Cancelled by signal {signal}")]
+    #[diagnostic(
+        code(nur::cancelled),
+        help("running tasks were cancelled, and tasks which were not yet started were skipped")
+    )]
+    Cancelled { signal: i32 },
+
+    #[error("Stopped by signal {signal}")]
+    #[diagnostic(
+        code(nur::killed),
+        help("nur was asked to stop again, so running tasks were killed without waiting for them")
+    )]
+    Killed { signal: i32 },
+}
+
+impl Error {
+    /// The code nur should exit with for this error, as documented in [`exit_code`].
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::MissingVersion
+            | Error::InvalidVersion(_)
+            | Error::UnsupportedVersion { .. }
+            | Error::NurfileNotFound { .. }
+            | Error::NurfileAlreadyExists { .. }
+            | Error::MultipleNurFilesFound { .. }
+            | Error::NurfileUnreadable { .. }
+            | Error::NurfileSyntaxError { .. } => exit_code::NURFILE,
+            Error::TaskCycle { .. } => exit_code::TASK_CYCLE,
+            Error::NoSuchTask { .. } => exit_code::NO_SUCH_TASK,
+            Error::ArgumentsWithMultipleTasks { .. } => exit_code::USAGE,
+            Error::Cancelled { signal } | Error::Killed { signal } => exit_code::signal(*signal),
+            Error::TaskFailed(failure) => failure.task_error.exit_code(),
+            Error::IoError(_)
+            | Error::InternalError(_)
+            | Error::LogDirectoryError { .. }
+            | Error::LogWriteError { .. }
+            | Error::ReportWriteError { .. }
+            | Error::Multiple { .. } => exit_code::FAILURE,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("Task ‘{task_name}’ failed")]
+pub struct TaskFailure {
+    pub task_name: String,
+    #[source]
+    pub task_error: TaskError,
+    pub help: Option<String>,
+    /// Problems found in the task’s output, shown with the source they refer to.
+    pub diagnostics: Vec<matcher::SourceDiagnostic>,
+}
+
+// implemented by hand, since the derived `related` would
+// render an empty section for tasks without diagnostics
+impl Diagnostic for TaskFailure {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new("nur::task_failed"))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.help
+            .as_ref()
+            .map(|help| Box::new(help) as Box<dyn Display>)
+    }
+
+    fn diagnostic_source(&self) -> Option<&dyn Diagnostic> {
+        Some(&self.task_error)
+    }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        if self.diagnostics.is_empty() {
+            return None;
+        }
+
+        Some(Box::new(
+            self.diagnostics.iter().map(|d| d as &dyn Diagnostic),
+        ))
+    }
 }
 
 pub(crate) fn internal_error(e: impl std::error::Error + Sync + Send + 'static) -> Error {
@@ -125,6 +244,8 @@ pub enum TaskError {
     Failed {
         command: String,
         exit_status: std::process::ExitStatus,
+        /// The line of the nurfile that the command is defined on, if known.
+        line: Option<usize>,
     },
 
     #[error("error starting executable ‘{executable}’: {kind}")]
@@ -142,14 +263,106 @@ pub enum TaskError {
     },
 }
 
+impl TaskError {
+    /// The exit code of the command which failed, or 128 plus the signal that killed it.
+    pub fn exit_code(&self) -> u8 {
+        let TaskError::Failed { exit_status, .. } = self else {
+            return exit_code::FAILURE;
+        };
+
+        if let Some(code) = exit_status.code() {
+            // only the low byte of an exit code is seen by a parent process,
+            // which must not be zero, since the command failed
+            return match u8::try_from(code & 0xff) {
+                Ok(0) | Err(_) => exit_code::FAILURE,
+                Ok(code) => code,
+            };
+        }
+
+        #[cfg(unix)]
+        if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(exit_status) {
+            return exit_code::signal(signal);
+        }
+
+        exit_code::FAILURE
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum TaskStatus {
-    StdOut(String),
-    StdErr(String),
-    Started {},
+    StdOut(Line),
+    StdErr(Line),
+    /// A line from a task whose stdout and stderr are read from the same pipe.
+    Combined(Line),
+    /// An intermediate state of a line which is being overwritten using
+    /// carriage returns, such as a progress bar. The line is eventually
+    /// sent in its final state as output, so this can be ignored.
+    Progress(Line),
+    Started {
+        time: std::time::SystemTime,
+    },
     Finished {
         result: std::result::Result<TaskResult, TaskError>,
+        time: std::time::SystemTime,
+        /// Problems found in the task’s output by the configured matchers.
+        diagnostics: Vec<matcher::Diagnostic>,
+        /// How many lines of the task’s output the highlighting rules found of each
+        /// severity, counted before any output is left out, so that all outputs agree.
+        counts: output::highlight::Counts,
     },
 }
 
 pub type StatusMessage = (usize, TaskStatus);
+
+/// A line of output from a task, without its line ending.
+///
+/// Tasks may write anything, so the line is decoded for display by replacing
+/// invalid UTF-8. The original bytes are kept in that case, for outputs
+/// such as log files which should reproduce the output exactly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    text: String,
+    /// The bytes of the line, if they were not valid UTF-8.
+    raw: Option<Vec<u8>>,
+}
+
+impl Line {
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        match String::from_utf8(bytes) {
+            Ok(text) => Line { text, raw: None },
+            Err(e) => Line {
+                text: String::from_utf8_lossy(e.as_bytes()).into_owned(),
+                raw: Some(e.into_bytes()),
+            },
+        }
+    }
+
+    /// The line as written by the task.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.raw.as_deref().unwrap_or(self.text.as_bytes())
+    }
+
+    pub fn into_string(self) -> String {
+        self.text
+    }
+}
+
+impl From<String> for Line {
+    fn from(text: String) -> Self {
+        Line { text, raw: None }
+    }
+}
+
+impl std::ops::Deref for Line {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl Display for Line {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text)
+    }
+}
@@ -0,0 +1,253 @@
+//! Problem matchers, which find diagnostics such as `src/main.c:3:14: error: …` in task output.
+
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use serde::Serialize;
+
+use crate::output::highlight::{strip_ansi, Severity, RUST_SUMMARIES};
+
+/// A problem reported by a tool, found in the output of a task.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The file as printed by the tool, which is usually relative to the task’s directory.
+    pub file: PathBuf,
+    pub line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+/// A pattern which finds diagnostics in lines of output.
+///
+/// The pattern’s named groups provide the parts of the diagnostic:
+/// `file`, `line`, `column`, `message`, and `severity`. When `severity` is not
+/// captured, or is not recognised, the matcher’s own severity is used.
+///
+/// Some tools print a message and its location on separate lines. A match with
+/// a `message` but no `file` is held until a following match which has a `file`
+/// but no `message`.
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    pub pattern: Regex,
+    pub severity: Severity,
+}
+
+impl Matcher {
+    fn new(pattern: &str, severity: Severity) -> Self {
+        Matcher {
+            pattern: Regex::new(pattern).expect("built-in patterns are valid"),
+            severity,
+        }
+    }
+}
+
+/// Matchers for the output of common tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// `rustc`, `cargo`, and `clippy`, in either the default or the short message format.
+    Rust,
+    /// `gcc` and `clang`.
+    Gcc,
+    /// `eslint`, with the `unix` formatter.
+    Eslint,
+    /// `pytest` tracebacks.
+    Pytest,
+}
+
+impl Preset {
+    pub fn matchers(self) -> Vec<Matcher> {
+        use Severity::*;
+        match self {
+            Preset::Rust => RUST_SUMMARIES
+                .iter()
+                .map(|pattern| Matcher::new(pattern, Ignore))
+                .chain([
+                    Matcher::new(
+                        r"^(?P<file>[^:\s]+):(?P<line>\d+):(?P<column>\d+): (?P<severity>error|warning)(\[\w+\])?: (?P<message>.+)$",
+                        Error,
+                    ),
+                    Matcher::new(
+                        r"^(?P<severity>error|warning)(\[\w+\])?: (?P<message>.+)$",
+                        Error,
+                    ),
+                    Matcher::new(
+                        r"^\s*--> (?P<file>[^:]+):(?P<line>\d+):(?P<column>\d+)$",
+                        Error,
+                    ),
+                ])
+                .collect(),
+            Preset::Gcc => vec![Matcher::new(
+                r"^(?P<file>[^:\s]+):(?P<line>\d+):((?P<column>\d+):)? (fatal )?(?P<severity>error|warning|note): (?P<message>.+)$",
+                Error,
+            )],
+            Preset::Eslint => vec![Matcher::new(
+                r"^(?P<file>[^:\s]+):(?P<line>\d+):(?P<column>\d+): (?P<message>.+) \[(?P<severity>Error|Warning)/[^\]]*\]$",
+                Error,
+            )],
+            Preset::Pytest => vec![Matcher::new(
+                r"^(?P<file>[^:\s]+\.py):(?P<line>\d+): (?P<message>\w*(Error|Exception)\b.*)$",
+                Error,
+            )],
+        }
+    }
+}
+
+/// Finds diagnostics in a single stream of output, one line at a time.
+pub struct Scanner<'a> {
+    matchers: &'a [Matcher],
+    /// A message waiting for its location.
+    pending: Option<(Severity, String)>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Scanner<'a> {
+    pub fn new(matchers: &'a [Matcher]) -> Self {
+        Scanner {
+            matchers,
+            pending: None,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    pub fn scan(&mut self, line: &str) {
+        if self.matchers.is_empty() {
+            return;
+        }
+
+        let line = strip_ansi(line);
+        if line.trim().is_empty() {
+            self.pending = None;
+            return;
+        }
+
+        let Some((matcher, captures)) = self
+            .matchers
+            .iter()
+            .find_map(|m| m.pattern.captures(&line).map(|c| (m, c)))
+        else {
+            return;
+        };
+
+        let severity = captures
+            .name("severity")
+            .and_then(|s| parse_severity(s.as_str()))
+            .unwrap_or(matcher.severity);
+
+        if severity == Severity::Ignore {
+            self.pending = None;
+            return;
+        }
+
+        let message = captures.name("message").map(|m| m.as_str().to_string());
+        let file = captures.name("file").map(|m| PathBuf::from(m.as_str()));
+        let line_number = captures
+            .name("line")
+            .and_then(|m| m.as_str().parse::<usize>().ok());
+
+        match (file, line_number, message) {
+            (Some(file), Some(line), message) => {
+                let (severity, message) = match message {
+                    Some(message) => (severity, message),
+                    None => match self.pending.take() {
+                        Some(pending) => pending,
+                        // a location on its own says nothing
+                        None => return,
+                    },
+                };
+
+                self.diagnostics.push(Diagnostic {
+                    severity,
+                    file,
+                    line,
+                    column: captures
+                        .name("column")
+                        .and_then(|m| m.as_str().parse().ok()),
+                    message,
+                });
+            }
+            (_, _, Some(message)) => self.pending = Some((severity, message)),
+            _ => {}
+        }
+    }
+
+    pub fn finish(self) -> Vec<Diagnostic> {
+        self.diagnostics
+    }
+}
+
+fn parse_severity(s: &str) -> Option<Severity> {
+    match s.to_ascii_lowercase().as_str() {
+        "error" | "fatal" | "failure" => Some(Severity::Error),
+        "warning" | "warn" => Some(Severity::Warning),
+        "note" | "info" | "help" => Some(Severity::Note),
+        _ => None,
+    }
+}
+
+/// A diagnostic shown with the lines of source it refers to.
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+#[error("{message}")]
+pub struct SourceDiagnostic {
+    message: String,
+    #[source_code]
+    source_code: Option<miette::NamedSource<String>>,
+    #[label("here")]
+    span: Option<miette::SourceSpan>,
+    #[help]
+    location: Option<String>,
+}
+
+impl SourceDiagnostic {
+    /// Reads the source of the diagnostic, relative to the directory the task ran in.
+    /// If the file cannot be read, its location is given instead.
+    pub fn new(diagnostic: &Diagnostic, cwd: &Path) -> Self {
+        let source = std::fs::read_to_string(cwd.join(&diagnostic.file)).ok();
+        let span = source
+            .as_deref()
+            .and_then(|source| offset_of(source, diagnostic.line, diagnostic.column.unwrap_or(1)));
+
+        match (source, span) {
+            (Some(source), Some(offset)) => SourceDiagnostic {
+                message: diagnostic.message.clone(),
+                source_code: Some(miette::NamedSource::new(
+                    diagnostic.file.to_string_lossy(),
+                    source,
+                )),
+                span: Some(offset.into()),
+                location: None,
+            },
+            _ => SourceDiagnostic {
+                message: diagnostic.message.clone(),
+                source_code: None,
+                span: None,
+                location: Some(format!(
+                    "reported at {}:{}{}",
+                    diagnostic.file.display(),
+                    diagnostic.line,
+                    diagnostic
+                        .column
+                        .map(|c| format!(":{c}"))
+                        .unwrap_or_default()
+                )),
+            },
+        }
+    }
+}
+
+/// Finds the byte offset of a 1-based line and column, where the column counts characters.
+fn offset_of(source: &str, line: usize, column: usize) -> Option<usize> {
+    let mut start = 0;
+    for _ in 1..line {
+        start += source[start..].find('\n')? + 1;
+    }
+
+    let text = source[start..].split('\n').next().unwrap_or_default();
+    let within = text
+        .char_indices()
+        .nth(column.saturating_sub(1))
+        .map_or(text.len(), |(ix, _)| ix);
+
+    Some(start + within)
+}
@@ -9,6 +9,8 @@ use std::{
     path::{Path, PathBuf},
 };
 
+use petgraph::graphmap::DiGraphMap;
+
 #[derive(Debug)]
 pub struct NurFile {
     pub version: crate::version::Version,
@@ -22,18 +24,137 @@ pub struct NurFile {
     pub env: BTreeMap<String, String>,
 }
 
+impl NurFile {
+    /// Builds a graph with an edge from each task to each of its dependencies.
+    /// Dependencies which are not defined as tasks are also included.
+    pub(crate) fn dependency_graph(&self) -> DiGraphMap<&str, ()> {
+        let mut graph: DiGraphMap<&str, ()> = DiGraphMap::new();
+        for (name, data) in &self.tasks {
+            graph.add_node(name);
+            for dep in &data.dependencies {
+                graph.add_edge(name, dep, ());
+            }
+        }
+
+        graph
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct Options {
     pub output: OutputOptions,
+
+    /// Print a summary of all task outcomes after running. With JSON output,
+    /// it is printed to stderr, so that stdout only has events.
+    pub summary: bool,
+
+    /// Write the output of each task to a file.
+    pub logs: Option<LogOptions>,
+
+    /// Whether to use the output format of the CI provider being run under.
+    pub ci: CiSetting,
+
+    /// Patterns which find diagnostics in task output.
+    pub matchers: Vec<crate::matcher::Matcher>,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CiSetting {
+    /// Use the output format of the detected CI provider, if any.
+    #[default]
+    Detect,
+    /// Always use the configured output format.
+    Disabled,
+    /// Use the output format of the given CI provider.
+    Provider(CiProvider),
+}
+
+impl CiSetting {
+    /// The CI provider whose output format is used, given the one detected, if any.
+    pub fn provider(self, detected: Option<CiProvider>) -> Option<CiProvider> {
+        match self {
+            CiSetting::Detect => detected,
+            CiSetting::Disabled => None,
+            CiSetting::Provider(provider) => Some(provider),
+        }
+    }
+}
+
+/// A CI system with its own syntax for collapsible sections of output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CiProvider {
+    GitHubActions,
+    GitLab,
+    AzurePipelines,
+    Buildkite,
+    TeamCity,
+}
+
+impl CiProvider {
+    /// Determines the CI provider from the environment variables it sets.
+    pub fn detect() -> Option<Self> {
+        let is_true =
+            |var: &str| std::env::var(var).is_ok_and(|value| value.eq_ignore_ascii_case("true"));
+
+        if is_true("GITHUB_ACTIONS") {
+            Some(CiProvider::GitHubActions)
+        } else if is_true("GITLAB_CI") {
+            Some(CiProvider::GitLab)
+        } else if is_true("TF_BUILD") {
+            Some(CiProvider::AzurePipelines)
+        } else if is_true("BUILDKITE") {
+            Some(CiProvider::Buildkite)
+        } else if std::env::var_os("TEAMCITY_VERSION").is_some() {
+            Some(CiProvider::TeamCity)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LogOptions {
+    /// The directory to write logs into, relative to the nurfile.
+    pub directory: PathBuf,
+
+    /// Start each line with the time it was output.
+    pub timestamps: bool,
+}
+
+#[derive(Debug, Default, Clone)]
 pub struct OutputOptions {
     pub style: OutputStyle,
     pub prefix: PrefixStyle,
+    pub templates: crate::output::template::Templates,
+    pub highlight: crate::output::highlight::Highlighter,
 }
 
-#[derive(Debug)]
+impl OutputOptions {
+    /// Streamed output using only ASCII characters, for terminals without Unicode support.
+    pub fn ascii() -> Self {
+        OutputOptions {
+            style: OutputStyle::Streamed {
+                separator: "|".to_string(),
+                separator_switch: Some("+".to_string()),
+            },
+            prefix: PrefixStyle::default(),
+            templates: crate::output::template::Templates::ascii(),
+            highlight: Default::default(),
+        }
+    }
+
+    /// Output grouped into the collapsible sections of a CI provider.
+    pub fn ci(provider: CiProvider) -> Self {
+        OutputOptions {
+            style: OutputStyle::Sections(provider),
+            prefix: PrefixStyle::NoPrefix,
+            templates: Default::default(),
+            highlight: Default::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 #[non_exhaustive]
 pub enum OutputStyle {
     Grouped {
@@ -42,20 +163,55 @@ pub enum OutputStyle {
         separator_last: Option<String>,
         only_on_failure: bool,
         deterministic: bool,
+        /// How many bytes of output may be held in memory;
+        /// beyond this, output is buffered in temporary files.
+        memory_limit: usize,
+        /// Show only the last lines of each task’s output.
+        max_lines: Option<usize>,
+        /// Show only the last lines of a failed task’s output.
+        failure_tail: Option<usize>,
     },
     Streamed {
         separator: String,
         separator_switch: Option<String>,
     },
+    /// Newline-delimited JSON events, for consumption by other programs.
+    Json,
+    /// A live view of running tasks. Only used when stdout is a terminal;
+    /// otherwise, streamed output is used.
+    Dashboard,
+    /// Each task’s output in a collapsible section, as understood by a CI provider.
+    Sections(CiProvider),
 }
 
-impl Default for OutputStyle {
-    fn default() -> Self {
+impl OutputStyle {
+    /// Streamed output with the default separators.
+    pub fn streamed() -> Self {
         OutputStyle::Streamed {
             separator: "│".to_string(),
             separator_switch: Some("┼".to_string()),
         }
     }
+
+    /// Grouped output with the default separators.
+    pub fn grouped() -> Self {
+        OutputStyle::Grouped {
+            separator: "│".to_string(),
+            separator_first: Some("╭".to_string()),
+            separator_last: Some("╰".to_string()),
+            only_on_failure: false,
+            deterministic: false,
+            memory_limit: crate::output::grouped::DEFAULT_MEMORY_LIMIT,
+            max_lines: None,
+            failure_tail: None,
+        }
+    }
+}
+
+impl Default for OutputStyle {
+    fn default() -> Self {
+        OutputStyle::streamed()
+    }
 }
 
 #[derive(Debug, Copy, Clone, Default)]
@@ -67,6 +223,40 @@ pub enum PrefixStyle {
     Aligned,
 }
 
+/// Overrides of the output options for a single task.
+/// These apply to the grouped and streamed output styles. CI sections use the
+/// mode but have no prefixes, and the JSON event stream always has every line.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct TaskOutputOptions {
+    pub mode: Option<TaskOutputMode>,
+    pub prefix: Option<PrefixStyle>,
+}
+
+/// How the output of a single task is shown.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TaskOutputMode {
+    /// Lines are shown as soon as they are written.
+    Streamed,
+    /// Lines are shown together once the task has finished.
+    Grouped,
+    /// Lines are shown together once the task has finished, only if it failed.
+    OnlyOnFailure,
+    /// Lines are never shown, only the task’s final status.
+    Quiet,
+}
+
+/// How a task’s stdout and stderr are read.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Streams {
+    /// Each stream has its own pipe, so lines are labelled with their stream,
+    /// but lines written to different streams may be shown out of order.
+    #[default]
+    Separate,
+    /// Both streams share a single pipe, so lines are shown in the order
+    /// they were written. Only supported on Unix; elsewhere streams are separate.
+    Combined,
+}
+
 #[derive(Debug)]
 pub struct Let {}
 
@@ -77,6 +267,13 @@ pub struct NurTask {
     pub dependencies: Vec<String>,
     pub commands: Vec<NurCommand>,
     pub cancellable: bool,
+    /// The line of the nurfile that the task is defined on, if known.
+    pub line: Option<usize>,
+    pub output: TaskOutputOptions,
+    pub streams: Streams,
+    /// Whether commands run on a pseudo-terminal, so that they behave as they
+    /// would interactively. Only supported on Linux.
+    pub pty: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -84,6 +281,8 @@ pub struct NurCommand {
     pub env: BTreeMap<String, String>,
     pub sh: String,
     pub ignore_result: bool,
+    /// The line of the nurfile that the command is defined on, if known.
+    pub line: Option<usize>,
 }
 
 pub fn load_config(initial_dir: &Path, file: Option<&Path>) -> crate::Result<(PathBuf, NurFile)> {
@@ -143,7 +342,11 @@ pub fn read_nurfile(initial_dir: &Path, file: Option<&Path>) -> crate::Result<(P
         None => find_nurfile(initial_dir, true)?,
     };
 
-    let contents = std::fs::read_to_string(&path).map_err(crate::Error::IoError)?;
+    let contents =
+        std::fs::read_to_string(&path).map_err(|source| crate::Error::NurfileUnreadable {
+            path: path.clone(),
+            source,
+        })?;
     match (parser)(&path, &contents) {
         Ok(parsed) => Ok((path, parsed)),
         Err(inner) => Err(crate::Error::NurfileSyntaxError { path, inner }),
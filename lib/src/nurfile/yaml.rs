@@ -3,6 +3,12 @@ use std::{collections::BTreeMap, convert::Infallible, path::Path};
 use miette::Diagnostic;
 use serde::Deserialize;
 use thiserror::Error;
+use yaml_rust2::parser::Event;
+
+use crate::output::{
+    highlight::{self, Highlighter},
+    template::{Template, Templates},
+};
 
 fn true_bool() -> bool {
     true
@@ -22,11 +28,75 @@ pub struct NurYaml {
     tasks: BTreeMap<String, Task>,
 }
 
+#[serde_with::serde_as]
 #[derive(Deserialize, Default)]
 #[serde(deny_unknown_fields)]
 pub struct Options {
     #[serde(default)]
     output: Option<OutputOptions>,
+
+    #[serde(default)]
+    summary: bool,
+
+    #[serde(default)]
+    #[serde_as(deserialize_as = "Option<serde_with::PickFirst<(_, serde_with::DisplayFromStr)>>")]
+    logs: Option<LogOptions>,
+
+    #[serde(default)]
+    ci: Option<Ci>,
+
+    #[serde(default)]
+    matchers: Vec<MatcherRule>,
+}
+
+/// Either the name of a preset, or a single matcher.
+#[serde_with::serde_as]
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum MatcherRule {
+    Preset(Preset),
+    Matcher {
+        #[serde_as(deserialize_as = "serde_with::DisplayFromStr")]
+        pattern: regex::Regex,
+        #[serde(default = "error_severity")]
+        severity: Severity,
+    },
+}
+
+fn error_severity() -> Severity {
+    Severity::Error
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ci {
+    Auto,
+    None,
+    Github,
+    Gitlab,
+    Azure,
+    Buildkite,
+    Teamcity,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LogOptions {
+    #[serde(alias = "dir")]
+    directory: std::path::PathBuf,
+
+    #[serde(default)]
+    timestamps: bool,
+}
+
+impl std::str::FromStr for LogOptions {
+    type Err = Infallible;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(LogOptions {
+            directory: s.into(),
+            timestamps: false,
+        })
+    }
 }
 
 #[serde_with::serde_as]
@@ -41,8 +111,82 @@ pub struct OutputOptions {
         deserialize_as = "Option<serde_with::PickFirst<(_, serde_with::FromInto<OutputStyleAliases>)>>"
     )]
     style: Option<OutputStyle>,
+
+    /// Use only ASCII characters in the default separators and templates.
+    #[serde(default)]
+    ascii: bool,
+
+    #[serde(default)]
+    templates: OutputTemplates,
+
+    #[serde(default)]
+    highlight: Vec<HighlightRule>,
 }
 
+/// Either the name of a preset, or a single rule.
+#[serde_with::serde_as]
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum HighlightRule {
+    Preset(Preset),
+    Rule {
+        #[serde_as(deserialize_as = "serde_with::DisplayFromStr")]
+        pattern: regex::Regex,
+        severity: Severity,
+    },
+}
+
+/// Tools with built-in highlighting rules and matchers.
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Preset {
+    #[serde(alias = "cargo", alias = "rustc")]
+    Rust,
+    #[serde(alias = "clang")]
+    Gcc,
+    Eslint,
+    Pytest,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+    Ignore,
+}
+
+#[serde_with::serde_as]
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct OutputTemplates {
+    #[serde(default)]
+    #[serde_as(deserialize_as = "Option<serde_with::DisplayFromStr>")]
+    prefix: Option<Template>,
+
+    #[serde(default)]
+    #[serde_as(deserialize_as = "Option<serde_with::DisplayFromStr>")]
+    start: Option<Template>,
+
+    #[serde(default)]
+    #[serde_as(deserialize_as = "Option<serde_with::DisplayFromStr>")]
+    finish: Option<Template>,
+
+    #[serde(default)]
+    #[serde_as(deserialize_as = "Option<serde_with::DisplayFromStr>")]
+    skip: Option<Template>,
+
+    #[serde(default)]
+    #[serde_as(deserialize_as = "Option<serde_with::DisplayFromStr>")]
+    cancel: Option<Template>,
+
+    #[serde(default)]
+    #[serde_as(deserialize_as = "Option<serde_with::DisplayFromStr>")]
+    failure: Option<Template>,
+}
+
+#[serde_with::serde_as]
 #[derive(Deserialize)]
 #[serde(rename_all = "snake_case")]
 #[serde(deny_unknown_fields)]
@@ -55,11 +199,22 @@ pub enum OutputStyle {
         deterministic: bool,
         #[serde(default)]
         only_on_failure: bool,
+        #[serde(default)]
+        #[serde_as(
+            deserialize_as = "Option<serde_with::PickFirst<(_, serde_with::DisplayFromStr)>>"
+        )]
+        memory_limit: Option<ByteSize>,
+        #[serde(default)]
+        max_lines: Option<usize>,
+        #[serde(default)]
+        failure_tail: Option<usize>,
     },
     Streamed {
         separator: Option<String>,
         separator_switch: Option<String>,
     },
+    Json,
+    Dashboard,
 }
 
 #[derive(Deserialize)]
@@ -67,6 +222,8 @@ pub enum OutputStyle {
 pub enum OutputStyleAliases {
     Streamed,
     Grouped,
+    Json,
+    Dashboard,
 }
 
 impl From<OutputStyleAliases> for OutputStyle {
@@ -82,11 +239,54 @@ impl From<OutputStyleAliases> for OutputStyle {
                 separator_end: None,
                 deterministic: false,
                 only_on_failure: false,
+                memory_limit: None,
+                max_lines: None,
+                failure_tail: None,
             },
+            OutputStyleAliases::Json => OutputStyle::Json,
+            OutputStyleAliases::Dashboard => OutputStyle::Dashboard,
         }
     }
 }
 
+/// A number of bytes, given either as an integer or with a unit, such as `64MB` or `1 GiB`.
+#[derive(Deserialize)]
+#[serde(transparent)]
+pub struct ByteSize(usize);
+
+#[derive(Debug, Error)]
+#[error("invalid size ‘{0}’ (expected a number of bytes, optionally followed by KB, MB, GB, KiB, MiB or GiB)")]
+pub struct ByteSizeError(String);
+
+impl std::str::FromStr for ByteSize {
+    type Err = ByteSizeError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ByteSizeError(s.to_string());
+        let trimmed = s.trim();
+        let split = trimmed
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(trimmed.len());
+        let (number, unit) = trimmed.split_at(split);
+
+        let multiplier: usize = match unit.trim().to_ascii_lowercase().as_str() {
+            "" | "b" => 1,
+            "k" | "kb" => 1000,
+            "m" | "mb" => 1000 * 1000,
+            "g" | "gb" => 1000 * 1000 * 1000,
+            "ki" | "kib" => 1 << 10,
+            "mi" | "mib" => 1 << 20,
+            "gi" | "gib" => 1 << 30,
+            _ => return Err(error()),
+        };
+
+        let number: usize = number.parse().map_err(|_| error())?;
+        number
+            .checked_mul(multiplier)
+            .map(ByteSize)
+            .ok_or_else(error)
+    }
+}
+
 #[derive(Deserialize)]
 enum Prefix {
     None,
@@ -123,6 +323,78 @@ pub struct Task {
 
     #[serde(default = "true_bool")]
     cancellable: bool,
+
+    #[serde(default)]
+    #[serde_as(
+        deserialize_as = "serde_with::PickFirst<(_, serde_with::FromInto<TaskOutputStyle>)>"
+    )]
+    output: TaskOutput,
+
+    #[serde(default)]
+    streams: Streams,
+
+    #[serde(default)]
+    pty: bool,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Streams {
+    #[default]
+    Separate,
+    Combined,
+}
+
+impl From<Streams> for crate::nurfile::Streams {
+    fn from(s: Streams) -> Self {
+        match s {
+            Streams::Separate => crate::nurfile::Streams::Separate,
+            Streams::Combined => crate::nurfile::Streams::Combined,
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct TaskOutput {
+    #[serde(default)]
+    style: Option<TaskOutputStyle>,
+
+    #[serde(default)]
+    prefix: Option<Prefix>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskOutputStyle {
+    Streamed,
+    Grouped,
+    OnlyOnFailure,
+    Quiet,
+}
+
+impl From<TaskOutputStyle> for TaskOutput {
+    fn from(style: TaskOutputStyle) -> Self {
+        TaskOutput {
+            style: Some(style),
+            prefix: None,
+        }
+    }
+}
+
+impl From<TaskOutput> for crate::nurfile::TaskOutputOptions {
+    fn from(o: TaskOutput) -> Self {
+        use crate::nurfile::TaskOutputMode;
+        crate::nurfile::TaskOutputOptions {
+            mode: o.style.map(|style| match style {
+                TaskOutputStyle::Streamed => TaskOutputMode::Streamed,
+                TaskOutputStyle::Grouped => TaskOutputMode::Grouped,
+                TaskOutputStyle::OnlyOnFailure => TaskOutputMode::OnlyOnFailure,
+                TaskOutputStyle::Quiet => TaskOutputMode::Quiet,
+            }),
+            prefix: o.prefix.map(Into::into),
+        }
+    }
 }
 
 #[derive(Deserialize, Default)]
@@ -164,6 +436,10 @@ impl From<NurYaml> for crate::nurfile::NurFile {
                         commands: t.run.into_iter().map(|x| x.into()).collect(),
                         dependencies: t.dependencies,
                         cancellable: t.cancellable,
+                        line: None,
+                        output: t.output.into(),
+                        streams: t.streams.into(),
+                        pty: t.pty,
                     },
                 )
             })),
@@ -175,42 +451,157 @@ impl From<Options> for crate::nurfile::Options {
     fn from(o: Options) -> Self {
         crate::nurfile::Options {
             output: o.output.map(Into::into).unwrap_or_default(),
+            summary: o.summary,
+            logs: o.logs.map(Into::into),
+            ci: o.ci.map(Into::into).unwrap_or_default(),
+            matchers: o
+                .matchers
+                .into_iter()
+                .flat_map(|rule| match rule {
+                    MatcherRule::Preset(preset) => crate::matcher::Preset::from(preset).matchers(),
+                    MatcherRule::Matcher { pattern, severity } => vec![crate::matcher::Matcher {
+                        pattern,
+                        severity: severity.into(),
+                    }],
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<Ci> for crate::nurfile::CiSetting {
+    fn from(ci: Ci) -> Self {
+        use crate::nurfile::{CiProvider, CiSetting};
+        match ci {
+            Ci::Auto => CiSetting::Detect,
+            Ci::None => CiSetting::Disabled,
+            Ci::Github => CiSetting::Provider(CiProvider::GitHubActions),
+            Ci::Gitlab => CiSetting::Provider(CiProvider::GitLab),
+            Ci::Azure => CiSetting::Provider(CiProvider::AzurePipelines),
+            Ci::Buildkite => CiSetting::Provider(CiProvider::Buildkite),
+            Ci::Teamcity => CiSetting::Provider(CiProvider::TeamCity),
+        }
+    }
+}
+
+impl From<LogOptions> for crate::nurfile::LogOptions {
+    fn from(o: LogOptions) -> Self {
+        crate::nurfile::LogOptions {
+            directory: o.directory,
+            timestamps: o.timestamps,
         }
     }
 }
 
 impl From<OutputOptions> for crate::nurfile::OutputOptions {
     fn from(o: OutputOptions) -> Self {
+        let defaults = if o.ascii {
+            crate::nurfile::OutputOptions::ascii()
+        } else {
+            crate::nurfile::OutputOptions::default()
+        };
+
+        let t = o.templates;
+        let templates = Templates {
+            prefix: t.prefix.or(defaults.templates.prefix),
+            start: t.start.unwrap_or(defaults.templates.start),
+            finish: t.finish.unwrap_or(defaults.templates.finish),
+            skip: t.skip.unwrap_or(defaults.templates.skip),
+            cancel: t.cancel.unwrap_or(defaults.templates.cancel),
+            failure: t.failure.unwrap_or(defaults.templates.failure),
+        };
+
         crate::nurfile::OutputOptions {
-            style: o.style.map(Into::into).unwrap_or_default(),
-            prefix: o.prefix.map(Into::into).unwrap_or_default(),
+            style: o
+                .style
+                .map(|style| style.into_style(o.ascii))
+                .unwrap_or(defaults.style),
+            prefix: o.prefix.map(Into::into).unwrap_or(defaults.prefix),
+            templates,
+            highlight: Highlighter::new(
+                o.highlight
+                    .into_iter()
+                    .flat_map(|rule| match rule {
+                        HighlightRule::Preset(preset) => highlight::Preset::from(preset).rules(),
+                        HighlightRule::Rule { pattern, severity } => vec![highlight::Rule {
+                            pattern,
+                            severity: severity.into(),
+                        }],
+                    })
+                    .collect(),
+            ),
         }
     }
 }
 
-impl From<OutputStyle> for crate::nurfile::OutputStyle {
-    fn from(o: OutputStyle) -> Self {
-        match o {
+impl OutputStyle {
+    fn into_style(self, ascii: bool) -> crate::nurfile::OutputStyle {
+        let default =
+            |unicode: &str, ascii_only: &str| if ascii { ascii_only } else { unicode }.to_string();
+
+        match self {
             OutputStyle::Grouped {
                 separator,
                 separator_end,
                 separator_start,
                 only_on_failure,
                 deterministic,
+                memory_limit,
+                max_lines,
+                failure_tail,
             } => crate::nurfile::OutputStyle::Grouped {
-                separator: separator.unwrap_or_else(|| "│".to_string()),
-                separator_first: Some(separator_start.unwrap_or_else(|| "╭".to_string())),
-                separator_last: Some(separator_end.unwrap_or_else(|| "╰".to_string())),
+                separator: separator.unwrap_or_else(|| default("│", "|")),
+                separator_first: Some(separator_start.unwrap_or_else(|| default("╭", "+"))),
+                separator_last: Some(separator_end.unwrap_or_else(|| default("╰", "+"))),
                 only_on_failure,
                 deterministic,
+                memory_limit: memory_limit
+                    .map_or(crate::output::grouped::DEFAULT_MEMORY_LIMIT, |b| b.0),
+                max_lines,
+                failure_tail,
             },
             OutputStyle::Streamed {
                 separator,
                 separator_switch,
             } => crate::nurfile::OutputStyle::Streamed {
-                separator: separator.unwrap_or_else(|| "│".to_string()),
-                separator_switch: Some(separator_switch.unwrap_or_else(|| "┼".to_string())),
+                separator: separator.unwrap_or_else(|| default("│", "|")),
+                separator_switch: Some(separator_switch.unwrap_or_else(|| default("┼", "+"))),
             },
+            OutputStyle::Json => crate::nurfile::OutputStyle::Json,
+            OutputStyle::Dashboard => crate::nurfile::OutputStyle::Dashboard,
+        }
+    }
+}
+
+impl From<Preset> for highlight::Preset {
+    fn from(p: Preset) -> Self {
+        match p {
+            Preset::Rust => highlight::Preset::Rust,
+            Preset::Gcc => highlight::Preset::Gcc,
+            Preset::Eslint => highlight::Preset::Eslint,
+            Preset::Pytest => highlight::Preset::Pytest,
+        }
+    }
+}
+
+impl From<Preset> for crate::matcher::Preset {
+    fn from(p: Preset) -> Self {
+        match p {
+            Preset::Rust => crate::matcher::Preset::Rust,
+            Preset::Gcc => crate::matcher::Preset::Gcc,
+            Preset::Eslint => crate::matcher::Preset::Eslint,
+            Preset::Pytest => crate::matcher::Preset::Pytest,
+        }
+    }
+}
+
+impl From<Severity> for highlight::Severity {
+    fn from(s: Severity) -> Self {
+        match s {
+            Severity::Error => highlight::Severity::Error,
+            Severity::Warning => highlight::Severity::Warning,
+            Severity::Note => highlight::Severity::Note,
+            Severity::Ignore => highlight::Severity::Ignore,
         }
     }
 }
@@ -231,13 +622,115 @@ impl From<Command> for crate::nurfile::NurCommand {
             env: c.environment,
             sh: c.sh,
             ignore_result: c.ignore_result,
+            line: None,
         }
     }
 }
 
 pub fn parse(path: &Path, input: &str) -> miette::Result<crate::nurfile::NurFile> {
     let nf: NurYaml = serde_yaml::from_str(input).map_err(|e| translate_error(path, e, input))?;
-    Ok(nf.into())
+    let mut nurfile: crate::nurfile::NurFile = nf.into();
+    locate_lines(&mut nurfile, input);
+    Ok(nurfile)
+}
+
+/// Finds the lines that tasks and their commands are defined on.
+///
+/// serde_yaml does not report locations, so the source is parsed again
+/// as a stream of events, each marked with where it starts.
+fn locate_lines(nurfile: &mut crate::nurfile::NurFile, input: &str) {
+    let mut events = Events(yaml_rust2::parser::Parser::new_from_str(input));
+    // the source has already been parsed, so errors are not expected here
+    _ = events.locate_tasks(nurfile);
+}
+
+struct Events<'a>(yaml_rust2::parser::Parser<std::str::Chars<'a>>);
+
+impl Events<'_> {
+    /// The next event and the line it starts on.
+    fn next(&mut self) -> Option<(Event, usize)> {
+        let (event, marker) = self.0.next_token().ok()?;
+        Some((event, marker.line()))
+    }
+
+    fn locate_tasks(&mut self, nurfile: &mut crate::nurfile::NurFile) -> Option<()> {
+        while !matches!(self.next()?.0, Event::MappingStart(..)) {}
+
+        // each task is a top-level key
+        while let (Event::Scalar(key, ..), line) = self.next()? {
+            let Some(task) = nurfile.tasks.get_mut(&key) else {
+                self.skip()?;
+                continue;
+            };
+
+            task.line = Some(line);
+            if !matches!(self.next()?.0, Event::MappingStart(..)) {
+                continue;
+            }
+
+            while let (Event::Scalar(key, ..), _) = self.next()? {
+                if key == "run" {
+                    self.locate_commands(&mut task.commands)?;
+                } else {
+                    self.skip()?;
+                }
+            }
+        }
+
+        Some(())
+    }
+
+    /// Locates the commands of a task, which may be a single command or a list of them.
+    fn locate_commands(&mut self, commands: &mut [crate::nurfile::NurCommand]) -> Option<()> {
+        let (event, line) = self.next()?;
+        if !matches!(event, Event::SequenceStart(..)) {
+            if let Some(command) = commands.first_mut() {
+                command.line = Some(line);
+            }
+
+            return self.skip_from(event);
+        }
+
+        for ix in 0.. {
+            let (event, line) = self.next()?;
+            if matches!(event, Event::SequenceEnd) {
+                break;
+            }
+
+            if let Some(command) = commands.get_mut(ix) {
+                command.line = Some(line);
+            }
+
+            self.skip_from(event)?;
+        }
+
+        Some(())
+    }
+
+    /// Skips the next value, including everything nested within it.
+    fn skip(&mut self) -> Option<()> {
+        let (event, _) = self.next()?;
+        self.skip_from(event)
+    }
+
+    /// Skips the rest of a value which began with the given event.
+    fn skip_from(&mut self, event: Event) -> Option<()> {
+        let mut depth = 0;
+        let mut event = event;
+        loop {
+            match event {
+                Event::MappingStart(..) | Event::SequenceStart(..) => depth += 1,
+                Event::MappingEnd | Event::SequenceEnd => depth -= 1,
+                _ => {}
+            }
+
+            if depth == 0 {
+                return Some(());
+            }
+
+            event = self.next()?.0;
+        }
+    }
 }
 
 // Present a serde_yaml::Error as a miette::Diagnostic
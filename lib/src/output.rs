@@ -1,16 +1,69 @@
 use std::fmt::Write;
 
+pub mod color;
+pub mod dashboard;
+pub mod github;
 pub mod grouped;
+pub mod highlight;
+pub mod json;
+pub mod junit;
+pub mod logs;
+pub mod sections;
 pub mod sink;
 pub mod streamed;
+pub mod summary;
+pub mod template;
 
+pub use color::{ColorChoice, Styles};
+pub use dashboard::Dashboard;
 pub use grouped::Grouped;
+pub use json::Json;
+pub use junit::JUnit;
+pub use logs::Logs;
+pub use sections::Sections;
 pub use streamed::Streamed;
+pub use summary::Summary;
 
 use crate::nurfile::*;
 
 pub trait Output<T> {
     fn handle(&mut self, msg: T);
+
+    /// Called periodically while tasks are running, for outputs which animate.
+    fn tick(&mut self) {}
+}
+
+/// Decides when outputs are next due a [`Output::tick`]. Ticks are due at a steady rate
+/// whether or not messages are arriving, so that tasks which print often do not hold them up.
+pub struct Ticker {
+    interval: std::time::Duration,
+    next: std::time::Instant,
+}
+
+impl Ticker {
+    pub fn new(interval: std::time::Duration) -> Self {
+        Ticker {
+            interval,
+            next: std::time::Instant::now() + interval,
+        }
+    }
+
+    /// How long to wait for a message before the next tick is due.
+    pub fn remaining(&self) -> std::time::Duration {
+        self.next
+            .saturating_duration_since(std::time::Instant::now())
+    }
+
+    /// Whether a tick is due, in which case the next one is scheduled.
+    pub fn due(&mut self) -> bool {
+        let now = std::time::Instant::now();
+        if now < self.next {
+            return false;
+        }
+
+        self.next = now + self.interval;
+        true
+    }
 }
 
 pub fn create<'a>(
@@ -18,35 +71,87 @@ pub fn create<'a>(
     stderr: &'a mut dyn std::io::Write,
     options: &OutputOptions,
     execution_order: &[&str],
+    color: ColorChoice,
+    log_directory: Option<&std::path::Path>,
+    task_outputs: &[TaskOutputOptions],
 ) -> Box<dyn Output<crate::StatusMessage> + 'a> {
+    if let OutputStyle::Dashboard = options.style {
+        if !std::io::IsTerminal::is_terminal(&std::io::stdout()) {
+            // the dashboard needs an interactive terminal
+            let options = OutputOptions {
+                style: OutputStyle::streamed(),
+                ..options.clone()
+            };
+
+            return create(
+                stdout,
+                stderr,
+                &options,
+                execution_order,
+                color,
+                log_directory,
+                task_outputs,
+            );
+        }
+    }
+
     let task_name_length_hint = execution_order
         .iter()
         .map(|x| x.len())
         .max()
         .unwrap_or_default();
 
-    let prefixer: Box<dyn Prefixer> = match options.prefix {
-        PrefixStyle::NoPrefix => Box::new(NullPrefixer {}),
-        PrefixStyle::Always => Box::new(AlwaysPrefixer {}),
-        PrefixStyle::Aligned => Box::new(AlignedPrefixer::new(task_name_length_hint)),
+    let make_prefixer = |style: PrefixStyle| -> Box<dyn Prefixer> {
+        match style {
+            PrefixStyle::NoPrefix => Box::new(NullPrefixer {}),
+            PrefixStyle::Always => Box::new(AlwaysPrefixer {}),
+            PrefixStyle::Aligned => Box::new(AlignedPrefixer::new(task_name_length_hint)),
+        }
+    };
+
+    let prefixer = make_prefixer(options.prefix);
+    let task_prefixers = task_outputs
+        .iter()
+        .map(|o| o.prefix.map(make_prefixer))
+        .collect();
+
+    // the output mode of each task, where it is overridden
+    let modes = |default: TaskOutputMode| -> Vec<TaskOutputMode> {
+        (0..execution_order.len())
+            .map(|ix| task_outputs.get(ix).and_then(|o| o.mode).unwrap_or(default))
+            .collect()
     };
 
     let output = sink::Sink { stdout, stderr };
-    let streamed =
-        |separator: &str, separator_first: &str, separator_switch: &str, separator_last: &str| {
-            Streamed::new(
-                output,
-                separator.to_string(),
-                separator_first.to_string(),
-                separator_switch.to_string(),
-                separator_last.to_string(),
-                execution_order
-                    .iter()
-                    .map(|name| name.to_string())
-                    .collect(),
-                prefixer,
-            )
-        };
+    let names = || -> Vec<String> {
+        execution_order
+            .iter()
+            .map(|name| name.to_string())
+            .collect()
+    };
+
+    let streamed = |output: sink::Sink<'a>,
+                    separator: &str,
+                    separator_first: &str,
+                    separator_switch: &str,
+                    separator_last: &str| {
+        Streamed::new(
+            output,
+            separator.to_string(),
+            separator_first.to_string(),
+            separator_switch.to_string(),
+            separator_last.to_string(),
+            names(),
+            prefixer,
+        )
+        .with_styles(
+            Styles::new(color.enabled(), execution_order),
+            Styles::new(color.enabled_for(&std::io::stderr()), execution_order),
+        )
+        .with_templates(options.templates.clone())
+        .with_highlighter(options.highlight.clone())
+        .with_task_prefixers(task_prefixers)
+    };
 
     match &options.style {
         OutputStyle::Grouped {
@@ -55,31 +160,95 @@ pub fn create<'a>(
             separator_first,
             separator_last,
             only_on_failure,
-        } => Box::new(Grouped::new(
-            streamed(
-                separator,
-                separator_first.as_ref().unwrap_or(separator),
-                separator,
-                separator_last.as_ref().unwrap_or(separator),
+            memory_limit,
+            max_lines,
+            failure_tail,
+        } => Box::new(
+            Grouped::new(
+                streamed(
+                    output,
+                    separator,
+                    separator_first.as_ref().unwrap_or(separator),
+                    separator,
+                    separator_last.as_ref().unwrap_or(separator),
+                ),
+                execution_order.len(),
+                *only_on_failure,
+                *deterministic,
+            )
+            .with_modes(modes(if *only_on_failure {
+                TaskOutputMode::OnlyOnFailure
+            } else {
+                TaskOutputMode::Grouped
+            }))
+            .with_memory_limit(*memory_limit)
+            .with_line_limits(*max_lines, *failure_tail)
+            .with_log_paths(
+                logs::file_names(execution_order)
+                    .into_iter()
+                    .map(|file_name| log_directory.map(|dir| dir.join(file_name)))
+                    .collect(),
             ),
-            execution_order.len(),
-            *only_on_failure,
-            *deterministic,
-        )),
+        ),
         OutputStyle::Streamed {
             separator,
             separator_switch,
-        } => Box::new(streamed(
-            separator,
-            separator,
-            separator_switch.as_ref().unwrap_or(separator),
-            separator,
+        } => {
+            let streamed = streamed(
+                output,
+                separator,
+                separator,
+                separator_switch.as_ref().unwrap_or(separator),
+                separator,
+            )
+            .with_progress(std::io::IsTerminal::is_terminal(&std::io::stdout()));
+
+            if task_outputs.iter().any(|o| o.mode.is_some()) {
+                // only tasks which override the mode are held back
+                Box::new(
+                    Grouped::new(streamed, execution_order.len(), false, false)
+                        .with_modes(modes(TaskOutputMode::Streamed)),
+                )
+            } else {
+                Box::new(streamed)
+            }
+        }
+        OutputStyle::Json => Box::new(Json::new(output, names())),
+        OutputStyle::Sections(provider) => Box::new(
+            Grouped::new(
+                Sections::new(output, *provider, names()),
+                execution_order.len(),
+                false,
+                true,
+            )
+            .with_modes(modes(TaskOutputMode::Grouped)),
+        ),
+        OutputStyle::Dashboard => Box::new(Dashboard::new(
+            output.stdout,
+            names(),
+            Styles::new(color.enabled(), execution_order),
         )),
     }
 }
 
+/// Formats a duration for humans, with precision appropriate to its size.
+pub(crate) fn format_duration(duration: std::time::Duration) -> String {
+    let secs = duration.as_secs();
+    if secs < 60 {
+        format!("{:.1}s", duration.as_secs_f64())
+    } else if secs < 60 * 60 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h {:02}m", secs / (60 * 60), (secs / 60) % 60)
+    }
+}
+
 pub trait Prefixer {
     fn prefix<'a: 's, 's>(&'s mut self, task_name: &'a str) -> &'s str;
+
+    /// Called when the output switches to another task, since
+    /// the prefixer may not have seen the output in between.
+    fn reset(&mut self) {}
 }
 
 struct AlwaysPrefixer {}
@@ -134,6 +303,10 @@ impl Prefixer for AlignedPrefixer {
             &self.first_prefix
         }
     }
+
+    fn reset(&mut self) {
+        self.last.clear();
+    }
 }
 
 struct NullPrefixer {}
@@ -0,0 +1,130 @@
+use std::io::IsTerminal;
+
+use owo_colors::{AnsiColors, Style};
+
+use crate::output::highlight::Severity;
+
+/// Whether output should be colored.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Color output written to a terminal, unless `NO_COLOR` is set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether output written to stdout should be colored.
+    pub fn enabled(self) -> bool {
+        self.enabled_for(&std::io::stdout())
+    }
+
+    /// Whether output written to the given stream should be colored.
+    pub fn enabled_for(self, stream: &impl IsTerminal) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                // see https://no-color.org/
+                let no_color = std::env::var_os("NO_COLOR").is_some_and(|x| !x.is_empty());
+                !no_color && stream.is_terminal()
+            }
+        }
+    }
+}
+
+/// Colors that tasks are distinguished by.
+/// Red, green and yellow are not included since they indicate failure,
+/// success, and tasks which were skipped or lines which are warnings.
+const TASK_COLORS: &[AnsiColors] = &[
+    AnsiColors::Cyan,
+    AnsiColors::Magenta,
+    AnsiColors::Blue,
+    AnsiColors::BrightCyan,
+    AnsiColors::BrightMagenta,
+    AnsiColors::BrightBlue,
+];
+
+/// The styles used when writing task output. If color is disabled, all styles are plain.
+#[derive(Clone, Default)]
+pub struct Styles {
+    /// The style of each task’s prefix, indexed by task ID.
+    pub tasks: Vec<Style>,
+    pub success: Style,
+    pub failure: Style,
+    pub skipped: Style,
+    /// The styles of highlighted lines of output.
+    pub error: Style,
+    pub warning: Style,
+    pub note: Style,
+}
+
+impl Styles {
+    pub fn new(enabled: bool, names: &[&str]) -> Self {
+        if !enabled {
+            return Styles {
+                tasks: vec![Style::new(); names.len()],
+                ..Default::default()
+            };
+        }
+
+        Styles {
+            tasks: task_colors(names)
+                .into_iter()
+                .map(|color| Style::new().color(color))
+                .collect(),
+            success: Style::new().green(),
+            failure: Style::new().red().bold(),
+            skipped: Style::new().yellow(),
+            error: Style::new().red(),
+            warning: Style::new().yellow(),
+            note: Style::new().cyan(),
+        }
+    }
+
+    pub fn task(&self, task_id: usize) -> Style {
+        self.tasks.get(task_id).copied().unwrap_or_default()
+    }
+
+    pub fn severity(&self, severity: Option<Severity>) -> Style {
+        match severity {
+            Some(Severity::Error) => self.error,
+            Some(Severity::Warning) => self.warning,
+            Some(Severity::Note) => self.note,
+            Some(Severity::Ignore) | None => Style::new(),
+        }
+    }
+}
+
+/// Assigns a color to each task.
+///
+/// A task’s color is derived from its name, so that it stays the same between runs.
+/// Tasks only share a color if there are more tasks than colors.
+fn task_colors(names: &[&str]) -> Vec<AnsiColors> {
+    let mut by_name: Vec<(usize, &str)> = names.iter().copied().enumerate().collect();
+    by_name.sort_by_key(|(_, name)| *name);
+
+    let mut taken = vec![false; TASK_COLORS.len()];
+    let mut colors = vec![TASK_COLORS[0]; names.len()];
+    for (task_id, name) in by_name {
+        let mut ix = fnv1a(name) as usize % TASK_COLORS.len();
+        if taken.iter().any(|t| !t) {
+            while taken[ix] {
+                ix = (ix + 1) % TASK_COLORS.len();
+            }
+        }
+
+        taken[ix] = true;
+        colors[task_id] = TASK_COLORS[ix];
+    }
+
+    colors
+}
+
+/// A simple hash which, unlike the standard library’s, is stable across releases.
+fn fnv1a(s: &str) -> u32 {
+    s.bytes().fold(0x811c9dc5, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x01000193)
+    })
+}
@@ -0,0 +1,270 @@
+use std::{
+    collections::VecDeque,
+    io::Write,
+    time::{Duration, Instant},
+};
+
+use owo_colors::OwoColorize;
+
+use crate::{
+    output::{format_duration, Styles},
+    StatusMessage, TaskError, TaskResult, TaskStatus,
+};
+
+const SPINNER: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const SPINNER_INTERVAL: Duration = Duration::from_millis(80);
+
+/// The most lines of output kept for each task, to be shown if it fails.
+const OUTPUT_LINES: usize = 1000;
+
+/// A live-updating view of all running tasks, for interactive terminals.
+///
+/// Each running task is shown with a spinner, its elapsed time, and its most
+/// recent line of output. Finished tasks collapse to a single line, and the
+/// full output of any failed tasks is printed once all tasks have finished.
+pub struct Dashboard<'a> {
+    out: &'a mut dyn Write,
+    names: Vec<String>,
+    styles: Styles,
+    tasks: Vec<State>,
+    started: Instant,
+    /// How many lines of the live area are currently on screen.
+    live_lines: usize,
+    /// Failed tasks, in the order they failed.
+    failed: Vec<usize>,
+    finished: bool,
+}
+
+enum State {
+    Waiting,
+    Running { started: Instant, output: Output },
+    Finished { output: Output },
+}
+
+/// The last lines of a task’s output.
+#[derive(Default)]
+struct Output {
+    lines: VecDeque<String>,
+    /// How many earlier lines were discarded.
+    omitted: usize,
+}
+
+impl Output {
+    fn push(&mut self, line: String) {
+        if self.lines.len() == OUTPUT_LINES {
+            self.lines.pop_front();
+            self.omitted += 1;
+        }
+
+        self.lines.push_back(line);
+    }
+}
+
+impl<'a> Dashboard<'a> {
+    pub fn new(out: &'a mut dyn Write, names: Vec<String>, styles: Styles) -> Self {
+        Dashboard {
+            out,
+            tasks: names.iter().map(|_| State::Waiting).collect(),
+            names,
+            styles,
+            started: Instant::now(),
+            live_lines: 0,
+            failed: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Replaces the live area, first printing any lines which are to stay above it.
+    fn redraw(&mut self, permanent: &[String]) {
+        use std::fmt::Write;
+
+        let mut frame = String::new();
+        if self.live_lines > 0 {
+            // move to the start of the live area and erase it
+            _ = write!(frame, "\x1b[{}A\x1b[J", self.live_lines);
+        }
+
+        for line in permanent {
+            _ = writeln!(frame, "{line}");
+        }
+
+        let width = textwrap::termwidth();
+        let tick = self.started.elapsed().as_millis() / SPINNER_INTERVAL.as_millis();
+        let spinner = SPINNER[tick as usize % SPINNER.len()];
+
+        self.live_lines = 0;
+        let mut waiting = 0;
+        for (task_id, state) in self.tasks.iter().enumerate() {
+            match state {
+                State::Waiting => waiting += 1,
+                State::Running { started, output } => {
+                    let head = format!("{spinner} {}", self.names[task_id]);
+                    let mut tail = format!(" {}", format_duration(started.elapsed()));
+                    if let Some(last) = output.lines.back() {
+                        tail.push_str(" │ ");
+                        tail.push_str(last);
+                    }
+
+                    let tail = truncate(&tail, width.saturating_sub(head.chars().count()));
+                    _ = writeln!(frame, "{}{tail}", head.style(self.styles.task(task_id)));
+                    self.live_lines += 1;
+                }
+                State::Finished { .. } => {}
+            }
+        }
+
+        if waiting > 0 {
+            _ = writeln!(frame, "  {waiting} waiting");
+            self.live_lines += 1;
+        }
+
+        _ = self.out.write_all(frame.as_bytes());
+        _ = self.out.flush();
+    }
+
+    /// Produces the line which a task collapses to once it has finished.
+    fn finished_line(&self, task_id: usize, result: &Result<TaskResult, TaskError>) -> String {
+        let name = &self.names[task_id];
+        let elapsed = match &self.tasks[task_id] {
+            State::Running { started, .. } => {
+                format!(" ({})", format_duration(started.elapsed()))
+            }
+            _ => String::new(),
+        };
+
+        match result {
+            Ok(TaskResult::RanToCompletion) => {
+                format!("{} {name}{elapsed}", "✔".style(self.styles.success))
+            }
+            Ok(TaskResult::Skipped) => {
+                format!("{}", format!("- {name} skipped").style(self.styles.skipped))
+            }
+            Ok(TaskResult::Cancelled) => format!(
+                "{}",
+                format!("- {name} cancelled{elapsed}").style(self.styles.skipped)
+            ),
+            Err(e) => format!(
+                "{} {name}{elapsed}: {}",
+                "✘".style(self.styles.failure),
+                e.style(self.styles.failure)
+            ),
+        }
+    }
+
+    /// Prints the full output of any failed tasks.
+    fn finish(&mut self) {
+        if self.finished {
+            return;
+        }
+
+        self.finished = true;
+        if self.live_lines > 0 {
+            _ = write!(self.out, "\x1b[{}A\x1b[J", self.live_lines);
+            self.live_lines = 0;
+        }
+
+        for &task_id in &self.failed {
+            let name = &self.names[task_id];
+            let style = self.styles.task(task_id);
+            let output = match &self.tasks[task_id] {
+                State::Finished { output } | State::Running { output, .. } => output,
+                State::Waiting => continue,
+            };
+
+            _ = writeln!(self.out);
+            _ = writeln!(
+                self.out,
+                "{}",
+                format!("╭╴ Output of ‘{name}’").style(style)
+            );
+            if output.omitted > 0 {
+                let lines = if output.omitted == 1 { "line" } else { "lines" };
+                _ = writeln!(
+                    self.out,
+                    "{}… {} earlier {lines} omitted",
+                    "│".style(style),
+                    output.omitted
+                );
+            }
+
+            for line in &output.lines {
+                _ = writeln!(self.out, "{}{line}", "│".style(style));
+            }
+
+            _ = writeln!(self.out, "{}", "╰╴".style(style));
+        }
+
+        _ = self.out.flush();
+    }
+}
+
+/// Shortens a line to fit within the given number of columns.
+fn truncate(line: &str, width: usize) -> String {
+    // control characters would disrupt the layout
+    let line = line.replace(|c: char| c.is_control(), " ");
+    if line.chars().count() <= width {
+        line
+    } else {
+        let mut result: String = line.chars().take(width.saturating_sub(1)).collect();
+        result.push('…');
+        result
+    }
+}
+
+impl crate::output::Output<StatusMessage> for Dashboard<'_> {
+    fn handle(&mut self, (task_id, status): StatusMessage) {
+        match status {
+            TaskStatus::Started { .. } => {
+                self.tasks[task_id] = State::Running {
+                    started: Instant::now(),
+                    output: Output::default(),
+                };
+
+                self.redraw(&[]);
+            }
+            TaskStatus::StdOut(line) | TaskStatus::StdErr(line) | TaskStatus::Combined(line) => {
+                // the screen is updated on the next tick, so that chatty tasks
+                // do not cause a redraw for every line
+                if let State::Running { output, .. } = &mut self.tasks[task_id] {
+                    output.push(line.into_string());
+                }
+            }
+            TaskStatus::Progress(_) => {}
+            TaskStatus::Finished { result, .. } => {
+                let line = self.finished_line(task_id, &result);
+                let output = match std::mem::replace(&mut self.tasks[task_id], State::Waiting) {
+                    // only the output of failed tasks is shown again
+                    State::Running { output, .. } if result.is_err() => output,
+                    _ => Output::default(),
+                };
+
+                if result.is_err() {
+                    self.failed.push(task_id);
+                }
+
+                self.tasks[task_id] = State::Finished { output };
+                self.redraw(&[line]);
+
+                if self
+                    .tasks
+                    .iter()
+                    .all(|state| matches!(state, State::Finished { .. }))
+                {
+                    self.finish();
+                }
+            }
+        }
+    }
+
+    fn tick(&mut self) {
+        if !self.finished {
+            self.redraw(&[]);
+        }
+    }
+}
+
+impl Drop for Dashboard<'_> {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
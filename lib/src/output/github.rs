@@ -0,0 +1,66 @@
+use std::{io::Write, path::Path};
+
+use crate::{nurfile::NurFile, Error, TaskError, TaskFailure};
+
+/// Writes an `::error` workflow command for each failed task,
+/// so that GitHub Actions shows it as an annotation.
+///
+/// Where the failure can be traced to a line of the nurfile,
+/// the annotation is attached to that line.
+pub fn write_annotations(
+    out: &mut dyn Write,
+    cwd: &Path,
+    nurfile_path: &Path,
+    config: &NurFile,
+    failures: &[Error],
+) -> std::io::Result<()> {
+    // annotations refer to files relative to the root of the repository
+    let root = std::env::var_os("GITHUB_WORKSPACE").map_or(cwd.to_owned(), Into::into);
+    let file = nurfile_path.strip_prefix(root).unwrap_or(nurfile_path);
+
+    for failure in failures {
+        let Error::TaskFailed(TaskFailure {
+            task_name,
+            task_error,
+            ..
+        }) = failure
+        else {
+            continue;
+        };
+
+        let line = match task_error {
+            TaskError::Failed { line, .. } => *line,
+            TaskError::ExecutableError { .. } | TaskError::ExecutableWaitFailure { .. } => None,
+        }
+        .or_else(|| config.tasks.get(task_name).and_then(|t| t.line));
+
+        let mut properties = vec![format!("file={}", escape_property(&file.to_string_lossy()))];
+        if let Some(line) = line {
+            properties.push(format!("line={line}"));
+        }
+
+        properties.push(format!(
+            "title={}",
+            escape_property(&format!("Task ‘{task_name}’ failed"))
+        ));
+
+        writeln!(
+            out,
+            "::error {}::{}",
+            properties.join(","),
+            escape_data(&task_error.to_string())
+        )?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn escape_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+pub(crate) fn escape_property(s: &str) -> String {
+    escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
@@ -1,26 +1,321 @@
-use crate::{StatusMessage, TaskStatus};
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Seek, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{nurfile::TaskOutputMode, Line, StatusMessage, TaskStatus};
+
+/// The default limit on how much output is held in memory before spilling to disk.
+pub const DEFAULT_MEMORY_LIMIT: usize = 64 * 1024 * 1024;
 
 pub struct Grouped<G> {
     logs: Vec<State>,
     deterministic: bool,
-    only_on_failure: bool,
+    /// How each task’s output is shown.
+    modes: Vec<TaskOutputMode>,
     inner: G,
+    memory_limit: usize,
+    /// The total size of output held in memory by all buffers.
+    memory_used: usize,
+    /// The most lines shown for any task.
+    max_lines: Option<usize>,
+    /// The most lines shown for a failed task.
+    failure_tail: Option<usize>,
+    /// Where the full output of each task is logged, if anywhere.
+    log_paths: Vec<Option<PathBuf>>,
 }
 
-#[derive(Clone)]
 enum State {
-    Appending(Vec<TaskStatus>),
-    ReadyToFlush(Vec<TaskStatus>),
+    Appending(Buffer),
+    ReadyToFlush(Buffer),
     Flushed,
 }
 
+/// The statuses of a single task, in order.
+///
+/// Output lines are held in memory until the memory limit is reached,
+/// after which they are moved to a temporary file. If only the last lines
+/// of output can be shown, the earlier lines are discarded instead.
+#[derive(Default)]
+struct Buffer {
+    started: Option<TaskStatus>,
+    /// Output lines which were moved out of memory; these precede `lines`.
+    spilled: Option<BufWriter<File>>,
+    spilled_lines: usize,
+    lines: VecDeque<TaskStatus>,
+    /// The size of `lines`.
+    bytes: usize,
+    /// How many lines were discarded from the front of `lines`.
+    discarded: usize,
+    finished: Option<TaskStatus>,
+}
+
+const STDOUT_TAG: u8 = b'o';
+const STDERR_TAG: u8 = b'e';
+const COMBINED_TAG: u8 = b'c';
+
+impl Buffer {
+    /// Adds a status, keeping at most `capacity` lines of output.
+    fn push(&mut self, status: TaskStatus, capacity: Option<usize>) {
+        match status {
+            TaskStatus::Started { .. } => self.started = Some(status),
+            TaskStatus::Finished { .. } => self.finished = Some(status),
+            // only the final state of each line is kept
+            TaskStatus::Progress(_) => {}
+            TaskStatus::StdOut(ref line)
+            | TaskStatus::StdErr(ref line)
+            | TaskStatus::Combined(ref line) => {
+                self.bytes += line.len();
+                self.lines.push_back(status);
+
+                if capacity.is_some_and(|capacity| self.lines.len() > capacity) {
+                    if let Some(
+                        TaskStatus::StdOut(line)
+                        | TaskStatus::StdErr(line)
+                        | TaskStatus::Combined(line),
+                    ) = self.lines.pop_front()
+                    {
+                        self.bytes -= line.len();
+                    }
+
+                    self.discarded += 1;
+                }
+            }
+        }
+    }
+
+    /// Moves all output lines held in memory to a temporary file.
+    fn spill(&mut self) -> std::io::Result<()> {
+        let file = match &mut self.spilled {
+            Some(file) => file,
+            None => self.spilled.insert(BufWriter::new(tempfile::tempfile()?)),
+        };
+
+        // each line is written with a tag for its stream;
+        // lines do not contain newlines, since output is split on them
+        for status in &self.lines {
+            let (tag, line) = match status {
+                TaskStatus::StdOut(line) => (STDOUT_TAG, line),
+                TaskStatus::StdErr(line) => (STDERR_TAG, line),
+                TaskStatus::Combined(line) => (COMBINED_TAG, line),
+                _ => unreachable!("only output lines are buffered"),
+            };
+
+            file.write_all(&[tag])?;
+            file.write_all(line.as_bytes())?;
+            file.write_all(b"\n")?;
+        }
+
+        self.spilled_lines += self.lines.len();
+        self.lines.clear();
+        self.bytes = 0;
+        Ok(())
+    }
+
+    /// Produces the statuses in the order they were received,
+    /// with at most `limit` lines of output. If any lines are omitted,
+    /// `omitted` produces a line saying how many.
+    fn drain(
+        self,
+        limit: Option<usize>,
+        omitted: impl FnOnce(usize) -> TaskStatus,
+        mut f: impl FnMut(TaskStatus),
+    ) {
+        if let Some(started) = self.started {
+            f(started);
+        }
+
+        let kept = self.spilled_lines + self.lines.len();
+        let mut skip = limit.map_or(0, |limit| kept.saturating_sub(limit));
+        if self.discarded + skip > 0 {
+            f(omitted(self.discarded + skip));
+        }
+
+        let mut f = |status| {
+            if skip > 0 {
+                skip -= 1;
+            } else {
+                f(status);
+            }
+        };
+
+        if let Some(spilled) = self.spilled {
+            if let Err(e) = read_spilled(spilled, &mut f) {
+                f(TaskStatus::StdErr(
+                    format!("[nur: unable to read buffered output: {e}]").into(),
+                ));
+            }
+        }
+
+        self.lines.into_iter().for_each(&mut f);
+
+        if let Some(finished) = self.finished {
+            f(finished);
+        }
+    }
+}
+
+fn read_spilled(spilled: BufWriter<File>, f: &mut impl FnMut(TaskStatus)) -> std::io::Result<()> {
+    let mut file = spilled.into_inner().map_err(|e| e.into_error())?;
+    file.rewind()?;
+
+    let mut reader = BufReader::new(file);
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            return Ok(());
+        }
+
+        if buf.last() == Some(&b'\n') {
+            buf.pop();
+        }
+
+        let Some((&tag, line)) = buf.split_first() else {
+            continue;
+        };
+
+        let line = Line::from_bytes(line.to_vec());
+        f(match tag {
+            STDERR_TAG => TaskStatus::StdErr(line),
+            COMBINED_TAG => TaskStatus::Combined(line),
+            _ => TaskStatus::StdOut(line),
+        });
+    }
+}
+
+/// The line shown in place of output which was omitted.
+fn omitted_note(omitted: usize, log_path: Option<&Path>) -> TaskStatus {
+    let lines = if omitted == 1 { "line" } else { "lines" };
+    let note = match log_path {
+        Some(path) => format!(
+            "… {omitted} earlier {lines} omitted; the full output is in {}",
+            path.display()
+        ),
+        None => format!("… {omitted} earlier {lines} omitted"),
+    };
+
+    TaskStatus::StdOut(note.into())
+}
+
 impl<G> Grouped<G> {
     pub fn new(inner: G, task_count: usize, only_on_failure: bool, deterministic: bool) -> Self {
+        let mode = if only_on_failure {
+            TaskOutputMode::OnlyOnFailure
+        } else {
+            TaskOutputMode::Grouped
+        };
+
         Grouped {
             inner,
             deterministic,
-            only_on_failure,
-            logs: vec![State::Appending(Vec::new()); task_count],
+            modes: vec![mode; task_count],
+            logs: (0..task_count)
+                .map(|_| State::Appending(Buffer::default()))
+                .collect(),
+            memory_limit: DEFAULT_MEMORY_LIMIT,
+            memory_used: 0,
+            max_lines: None,
+            failure_tail: None,
+            log_paths: Vec::new(),
+        }
+    }
+
+    /// Limits how many lines are shown for each task, keeping only the last lines.
+    /// `failure_tail` applies only to failed tasks.
+    pub fn with_line_limits(self, max_lines: Option<usize>, failure_tail: Option<usize>) -> Self {
+        Grouped {
+            max_lines,
+            failure_tail,
+            ..self
+        }
+    }
+
+    /// Sets how each task’s output is shown. Output of tasks which are
+    /// [streamed](TaskOutputMode::Streamed) is passed on as soon as it arrives.
+    pub fn with_modes(self, modes: Vec<TaskOutputMode>) -> Self {
+        Grouped { modes, ..self }
+    }
+
+    /// Sets where the full output of each task can be found, for when lines are omitted.
+    pub fn with_log_paths(self, log_paths: Vec<Option<PathBuf>>) -> Self {
+        Grouped { log_paths, ..self }
+    }
+
+    /// The most lines shown for a task which succeeded.
+    fn success_limit(&self, task_id: usize) -> Option<usize> {
+        match self.modes[task_id] {
+            TaskOutputMode::OnlyOnFailure | TaskOutputMode::Quiet => Some(0),
+            TaskOutputMode::Grouped | TaskOutputMode::Streamed => self.max_lines,
+        }
+    }
+
+    /// The most lines shown for a task which failed.
+    fn failure_limit(&self, task_id: usize) -> Option<usize> {
+        if self.modes[task_id] == TaskOutputMode::Quiet {
+            return Some(0);
+        }
+
+        match (self.max_lines, self.failure_tail) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// How many lines must be kept for a task, before it is known whether it failed.
+    fn capacity(&self, task_id: usize) -> Option<usize> {
+        Some(
+            self.success_limit(task_id)?
+                .max(self.failure_limit(task_id)?),
+        )
+    }
+
+    /// Sets how much output may be held in memory before it is moved to temporary files.
+    pub fn with_memory_limit(self, memory_limit: usize) -> Self {
+        Grouped {
+            memory_limit,
+            ..self
+        }
+    }
+
+    /// Spills the largest buffers until memory use is within the limit.
+    /// Buffers which only keep their last lines are never spilled.
+    fn enforce_memory_limit(&mut self) {
+        // this is checked for every line, so it should be cheap when within the limit
+        if self.memory_used <= self.memory_limit {
+            return;
+        }
+
+        let unbounded: Vec<bool> = (0..self.logs.len())
+            .map(|task_id| self.capacity(task_id).is_none())
+            .collect();
+
+        while self.memory_used > self.memory_limit {
+            let largest = self
+                .logs
+                .iter_mut()
+                .zip(&unbounded)
+                .filter_map(|(state, unbounded)| match state {
+                    State::Appending(buffer) | State::ReadyToFlush(buffer) if *unbounded => {
+                        Some(buffer)
+                    }
+                    _ => None,
+                })
+                .max_by_key(|buffer| buffer.bytes);
+
+            let Some(buffer) = largest.filter(|buffer| buffer.bytes > 0) else {
+                return;
+            };
+
+            let bytes = buffer.bytes;
+            if buffer.spill().is_err() {
+                // keep the output in memory rather than lose it
+                return;
+            }
+
+            self.memory_used -= bytes;
         }
     }
 }
@@ -28,71 +323,94 @@ impl<G> Grouped<G> {
 impl<G: crate::output::Output<StatusMessage>> Grouped<G> {
     fn flush(&mut self, task_id: usize) {
         let state = std::mem::replace(&mut self.logs[task_id], State::Flushed);
-        let statuses = match state {
-            State::Appending(x) => x,
+        let buffer = match state {
             State::ReadyToFlush(x) => x,
+            State::Appending(_) => unreachable!("not finished"),
             State::Flushed => unreachable!("already flushed"),
         };
 
-        for status in statuses {
-            self.inner.handle((task_id, status));
+        self.memory_used -= buffer.bytes;
+
+        let succeeded = matches!(
+            buffer.finished,
+            Some(TaskStatus::Finished { result: Ok(_), .. })
+        );
+
+        let status_only = match self.modes[task_id] {
+            TaskOutputMode::Quiet => true,
+            TaskOutputMode::OnlyOnFailure => succeeded,
+            TaskOutputMode::Grouped | TaskOutputMode::Streamed => false,
+        };
+
+        if status_only {
+            // only output the final status line
+            if let Some(finished) = buffer.finished {
+                self.inner.handle((task_id, finished));
+            }
+        } else {
+            let limit = if succeeded {
+                self.success_limit(task_id)
+            } else {
+                self.failure_limit(task_id)
+            };
+
+            let log_path = self.log_paths.get(task_id).and_then(Option::as_deref);
+            buffer.drain(
+                limit,
+                |omitted| omitted_note(omitted, log_path),
+                |status| self.inner.handle((task_id, status)),
+            );
         }
     }
 }
 
 impl<G: crate::output::Output<StatusMessage>> crate::output::Output<StatusMessage> for Grouped<G> {
     fn handle(&mut self, (task_id, status): crate::StatusMessage) {
-        match status {
-            TaskStatus::Finished { result } => {
-                let state = std::mem::replace(&mut self.logs[task_id], State::Flushed);
-                let mut statuses = match state {
-                    State::Appending(v) => v,
-                    State::ReadyToFlush(v) => v,
-                    State::Flushed => unreachable!("already flushed"),
-                };
-
-                if self.deterministic {
-                    // all previous outputs must be flushed
-                    for id in 0..task_id {
-                        match &self.logs[id] {
-                            State::Appending(_) => {
-                                // previous one is still pending,
-                                // mark ourselves as ready to flush
-                                statuses.push(TaskStatus::Finished { result });
-                                let r = std::mem::replace(
-                                    &mut self.logs[task_id],
-                                    State::ReadyToFlush(statuses),
-                                );
-                                debug_assert!(matches!(r, State::Flushed));
-                                return;
-                            }
-                            State::ReadyToFlush(_) => {
-                                self.flush(id);
-                            }
-                            State::Flushed => {}
-                        }
-                    }
-                }
+        if self.modes[task_id] == TaskOutputMode::Streamed {
+            let finished = matches!(status, TaskStatus::Finished { .. });
+            self.inner.handle((task_id, status));
+            if finished {
+                self.logs[task_id] = State::Flushed;
+            }
 
-                if self.only_on_failure && result.is_ok() {
-                    self.inner
-                        .handle((task_id, TaskStatus::Finished { result }));
-                } else {
-                    statuses.push(TaskStatus::Finished { result });
-                    for msg in statuses {
-                        self.inner.handle((task_id, msg));
-                    }
+            return;
+        }
+
+        let capacity = self.capacity(task_id);
+        let buffer = match &mut self.logs[task_id] {
+            State::Appending(x) => x,
+            State::ReadyToFlush(_) => unreachable!("already finished"),
+            State::Flushed => unreachable!("already flushed"),
+        };
+
+        let finished = matches!(status, TaskStatus::Finished { .. });
+        let before = buffer.bytes;
+        buffer.push(status, capacity);
+        // the buffer may shrink, if it discarded a line longer than the one added
+        self.memory_used = self.memory_used - before + buffer.bytes;
+
+        if !finished {
+            self.enforce_memory_limit();
+            return;
+        }
+
+        let buffer = std::mem::take(buffer);
+        self.logs[task_id] = State::ReadyToFlush(buffer);
+
+        if self.deterministic {
+            // flush everything that is finished, in order,
+            // up until the first task that is still pending
+            for id in 0..self.logs.len() {
+                match &self.logs[id] {
+                    // streamed tasks do not hold up the others
+                    State::Appending(_) if self.modes[id] == TaskOutputMode::Streamed => {}
+                    State::Appending(_) => break,
+                    State::ReadyToFlush(_) => self.flush(id),
+                    State::Flushed => {}
                 }
             }
-            status => {
-                let statuses = match &mut self.logs[task_id] {
-                    State::Appending(x) => x,
-                    State::ReadyToFlush(x) => x,
-                    State::Flushed => unreachable!("already flushed"),
-                };
-
-                statuses.push(status);
-            }
+        } else {
+            self.flush(task_id);
         }
     }
 }
@@ -0,0 +1,194 @@
+use std::borrow::Cow;
+
+use regex::Regex;
+
+/// How serious a line of output is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+    /// Lines which are not highlighted, even if a later rule would match them.
+    /// Used for lines which only restate other diagnostics, such as “3 warnings emitted”.
+    Ignore,
+}
+
+/// A pattern which identifies lines of a given severity.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub pattern: Regex,
+    pub severity: Severity,
+}
+
+impl Rule {
+    fn new(pattern: &str, severity: Severity) -> Self {
+        Rule {
+            pattern: Regex::new(pattern).expect("built-in patterns are valid"),
+            severity,
+        }
+    }
+}
+
+/// Lines in which `rustc` and `cargo` only restate other diagnostics.
+/// These are ignored by both the highlighting rules and the problem matchers.
+pub(crate) const RUST_SUMMARIES: &[&str] = &[
+    r"^(warning|error): .* generated \d+ warnings?",
+    r"^warning: \d+ warnings? emitted",
+    r"^error: aborting due to",
+    r"^error: could not compile",
+];
+
+/// Sets of rules for the output of common tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// `rustc`, `cargo`, and `clippy`.
+    Rust,
+    /// `gcc` and `clang`.
+    Gcc,
+    /// `eslint`, with either the default or the `unix` formatter.
+    Eslint,
+    /// `pytest`.
+    Pytest,
+}
+
+impl Preset {
+    pub fn rules(self) -> Vec<Rule> {
+        use Severity::*;
+        match self {
+            Preset::Rust => RUST_SUMMARIES
+                .iter()
+                .map(|pattern| Rule::new(pattern, Ignore))
+                .chain([
+                    Rule::new(r"^error(\[E\d+\])?:", Error),
+                    Rule::new(r"^warning(\[E\d+\])?:", Warning),
+                    Rule::new(r"^note:", Note),
+                ])
+                .collect(),
+            Preset::Gcc => vec![
+                Rule::new(r"^[^:\s]+:\d+(:\d+)?: (fatal )?error:", Error),
+                Rule::new(r"^[^:\s]+:\d+(:\d+)?: warning:", Warning),
+                Rule::new(r"^[^:\s]+:\d+(:\d+)?: note:", Note),
+            ],
+            Preset::Eslint => vec![
+                Rule::new(r"^\s+\d+:\d+\s+error\s", Error),
+                Rule::new(r"^\s+\d+:\d+\s+warning\s", Warning),
+                Rule::new(r"^\S+:\d+:\d+: .*\[Error/", Error),
+                Rule::new(r"^\S+:\d+:\d+: .*\[Warning/", Warning),
+            ],
+            Preset::Pytest => vec![
+                Rule::new(r"^(FAILED|ERROR) ", Error),
+                Rule::new(r"^\s*\S+:\d+: \w*Warning:", Warning),
+            ],
+        }
+    }
+}
+
+/// Classifies lines of task output by the first rule which matches them.
+#[derive(Debug, Clone, Default)]
+pub struct Highlighter {
+    rules: Vec<Rule>,
+}
+
+impl Highlighter {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Highlighter { rules }
+    }
+
+    pub fn severity(&self, line: &str) -> Option<Severity> {
+        if self.rules.is_empty() {
+            return None;
+        }
+
+        let line = strip_ansi(line);
+        self.rules
+            .iter()
+            .find(|rule| rule.pattern.is_match(&line))
+            .map(|rule| rule.severity)
+            .filter(|severity| *severity != Severity::Ignore)
+    }
+}
+
+/// Removes terminal escape sequences, so that colored output can be matched.
+pub(crate) fn strip_ansi(line: &str) -> Cow<'_, str> {
+    if !line.contains('\x1b') {
+        return Cow::Borrowed(line);
+    }
+
+    let mut result = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            if chars.next() == Some('[') {
+                // skip parameters up to the final byte
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+        } else {
+            result.push(c);
+        }
+    }
+
+    Cow::Owned(result)
+}
+
+/// The number of lines of each severity in a task’s output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counts {
+    pub errors: usize,
+    pub warnings: usize,
+    pub notes: usize,
+}
+
+impl Counts {
+    pub fn add(&mut self, severity: Severity) {
+        match severity {
+            Severity::Error => self.errors += 1,
+            Severity::Warning => self.warnings += 1,
+            Severity::Note => self.notes += 1,
+            Severity::Ignore => {}
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Counts::default()
+    }
+}
+
+impl std::ops::AddAssign for Counts {
+    fn add_assign(&mut self, other: Self) {
+        self.errors += other.errors;
+        self.warnings += other.warnings;
+        self.notes += other.notes;
+    }
+}
+
+/// Formats counts as, for example, “3 warnings, 1 error”.
+impl std::fmt::Display for Counts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parts = [
+            (self.warnings, "warning", "warnings"),
+            (self.errors, "error", "errors"),
+            (self.notes, "note", "notes"),
+        ];
+
+        let mut first = true;
+        for (count, singular, plural) in parts {
+            if count == 0 {
+                continue;
+            }
+
+            if !first {
+                f.write_str(", ")?;
+            }
+
+            first = false;
+            write!(f, "{count} {}", if count == 1 { singular } else { plural })?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+use std::time::SystemTime;
+
+use serde::Serialize;
+
+use crate::{
+    commands::Message, matcher::Diagnostic, StatusMessage, TaskError, TaskResult, TaskStatus,
+};
+
+/// Writes every status message as a line of JSON, for consumption by other programs.
+pub struct Json<O> {
+    output: O,
+    names: Vec<String>,
+}
+
+impl<O> Json<O> {
+    pub fn new(output: O, names: Vec<String>) -> Self {
+        Json { output, names }
+    }
+}
+
+#[derive(Serialize)]
+struct Event<'a> {
+    timestamp: String,
+    task: &'a str,
+    task_id: usize,
+    #[serde(flatten)]
+    kind: EventKind<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum EventKind<'a> {
+    Started,
+    Stdout {
+        line: &'a str,
+    },
+    Stderr {
+        line: &'a str,
+    },
+    Combined {
+        line: &'a str,
+    },
+    Finished {
+        result: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        exit_code: Option<i32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+        #[serde(skip_serializing_if = "<[_]>::is_empty")]
+        diagnostics: &'a [Diagnostic],
+    },
+}
+
+impl<'a> EventKind<'a> {
+    fn finished(result: &Result<TaskResult, TaskError>, diagnostics: &'a [Diagnostic]) -> Self {
+        match result {
+            Ok(result) => EventKind::Finished {
+                result: match result {
+                    TaskResult::Skipped => "skipped",
+                    TaskResult::Cancelled => "cancelled",
+                    TaskResult::RanToCompletion => "completed",
+                },
+                exit_code: None,
+                error: None,
+                diagnostics,
+            },
+            Err(e) => EventKind::Finished {
+                result: "failed",
+                exit_code: match e {
+                    TaskError::Failed { exit_status, .. } => exit_status.code(),
+                    _ => None,
+                },
+                error: Some(e.to_string()),
+                diagnostics,
+            },
+        }
+    }
+}
+
+impl<O: crate::output::Output<Message>> crate::output::Output<StatusMessage> for Json<O> {
+    fn handle(&mut self, (task_id, status): StatusMessage) {
+        // output lines are timestamped upon receipt
+        let (kind, time) = match &status {
+            // only the final state of each line is reported
+            TaskStatus::Progress(_) => return,
+            TaskStatus::StdOut(line) => (EventKind::Stdout { line }, SystemTime::now()),
+            TaskStatus::StdErr(line) => (EventKind::Stderr { line }, SystemTime::now()),
+            TaskStatus::Combined(line) => (EventKind::Combined { line }, SystemTime::now()),
+            TaskStatus::Started { time } => (EventKind::Started, *time),
+            TaskStatus::Finished {
+                result,
+                time,
+                diagnostics,
+                ..
+            } => (EventKind::finished(result, diagnostics), *time),
+        };
+
+        let event = Event {
+            timestamp: humantime::format_rfc3339_millis(time).to_string(),
+            task: &self.names[task_id],
+            task_id,
+            kind,
+        };
+
+        let line = serde_json::to_string(&event).expect("event should always serialize");
+        self.output.handle(Message::Out(line));
+    }
+}
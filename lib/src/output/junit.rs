@@ -0,0 +1,191 @@
+use std::{io::Write, time::SystemTime};
+
+use crate::{StatusMessage, TaskError, TaskResult, TaskStatus};
+
+/// Collects the outcome and output of each task, to be written as a JUnit XML report.
+///
+/// Each task that was run (or skipped) becomes one `<testcase>`.
+pub struct JUnit {
+    suite_name: String,
+    started: SystemTime,
+    tasks: Vec<TaskReport>,
+}
+
+struct TaskReport {
+    name: String,
+    started: Option<SystemTime>,
+    finished: Option<(SystemTime, Result<TaskResult, TaskError>)>,
+    stdout: String,
+    stderr: String,
+}
+
+impl TaskReport {
+    fn seconds(&self) -> f64 {
+        match (self.started, &self.finished) {
+            (Some(started), Some((finished, _))) => finished
+                .duration_since(started)
+                .unwrap_or_default()
+                .as_secs_f64(),
+            _ => 0.0,
+        }
+    }
+}
+
+impl JUnit {
+    pub fn new(suite_name: String, names: Vec<String>) -> Self {
+        JUnit {
+            suite_name,
+            started: SystemTime::now(),
+            tasks: names
+                .into_iter()
+                .map(|name| TaskReport {
+                    name,
+                    started: None,
+                    finished: None,
+                    stdout: String::new(),
+                    stderr: String::new(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn write(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        // tasks which never finished were not run at all,
+        // so they do not appear in the report
+        let tasks: Vec<&TaskReport> = self
+            .tasks
+            .iter()
+            .filter(|task| task.finished.is_some())
+            .collect();
+
+        let failures = tasks
+            .iter()
+            .filter(|task| matches!(task.finished, Some((_, Err(_)))))
+            .count();
+
+        let skipped = tasks
+            .iter()
+            .filter(|task| {
+                matches!(
+                    task.finished,
+                    Some((_, Ok(TaskResult::Skipped | TaskResult::Cancelled)))
+                )
+            })
+            .count();
+
+        let elapsed = SystemTime::now()
+            .duration_since(self.started)
+            .unwrap_or_default()
+            .as_secs_f64();
+
+        let counts = format!(
+            r#"tests="{}" failures="{failures}" errors="0" skipped="{skipped}" time="{elapsed:.3}""#,
+            tasks.len()
+        );
+
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(out, r#"<testsuites name="nur" {counts}>"#)?;
+        writeln!(
+            out,
+            r#"  <testsuite name="{}" {counts} timestamp="{}">"#,
+            escape(&self.suite_name),
+            humantime::format_rfc3339_seconds(self.started),
+        )?;
+
+        for task in tasks {
+            writeln!(
+                out,
+                r#"    <testcase name="{}" classname="{}" time="{:.3}">"#,
+                escape(&task.name),
+                escape(&self.suite_name),
+                task.seconds(),
+            )?;
+
+            match &task.finished {
+                Some((_, Ok(TaskResult::Skipped))) => {
+                    writeln!(out, r#"      <skipped message="a dependency failed"/>"#)?
+                }
+                Some((_, Ok(TaskResult::Cancelled))) => {
+                    writeln!(out, r#"      <skipped message="cancelled"/>"#)?
+                }
+                Some((_, Err(e))) => writeln!(
+                    out,
+                    r#"      <failure message="{}" type="{}"/>"#,
+                    escape(&e.to_string()),
+                    failure_type(e),
+                )?,
+                Some((_, Ok(TaskResult::RanToCompletion))) | None => {}
+            }
+
+            if !task.stdout.is_empty() {
+                writeln!(
+                    out,
+                    "      <system-out>{}</system-out>",
+                    escape(&task.stdout)
+                )?;
+            }
+
+            if !task.stderr.is_empty() {
+                writeln!(
+                    out,
+                    "      <system-err>{}</system-err>",
+                    escape(&task.stderr)
+                )?;
+            }
+
+            writeln!(out, "    </testcase>")?;
+        }
+
+        writeln!(out, "  </testsuite>")?;
+        writeln!(out, "</testsuites>")
+    }
+}
+
+fn failure_type(e: &TaskError) -> &'static str {
+    match e {
+        TaskError::Failed { .. } => "shell_command_failed",
+        TaskError::ExecutableError { .. } => "executable_start_error",
+        TaskError::ExecutableWaitFailure { .. } => "executable_wait_error",
+    }
+}
+
+/// Escapes text for use in XML content or attributes.
+///
+/// Characters which are not permitted in XML 1.0 at all
+/// (such as the escape character used by terminal colors) are dropped.
+fn escape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            '\t' | '\n' | '\r' => result.push(c),
+            c if c < ' ' || c == '\u{FFFE}' || c == '\u{FFFF}' => {}
+            c => result.push(c),
+        }
+    }
+
+    result
+}
+
+impl crate::output::Output<StatusMessage> for JUnit {
+    fn handle(&mut self, (task_id, status): StatusMessage) {
+        let task = &mut self.tasks[task_id];
+        match status {
+            TaskStatus::StdOut(line) | TaskStatus::Combined(line) => {
+                task.stdout.push_str(&line);
+                task.stdout.push('\n');
+            }
+            TaskStatus::StdErr(line) => {
+                task.stderr.push_str(&line);
+                task.stderr.push('\n');
+            }
+            TaskStatus::Progress(_) => {}
+            TaskStatus::Started { time } => task.started = Some(time),
+            TaskStatus::Finished { result, time, .. } => task.finished = Some((time, result)),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use std::{
+    collections::BTreeSet,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{StatusMessage, TaskStatus};
+
+/// Writes the output of each task to its own file in a directory.
+pub struct Logs {
+    timestamps: bool,
+    tasks: Vec<TaskLog>,
+    /// The first log which could not be written.
+    error: Option<(PathBuf, std::io::Error)>,
+}
+
+struct TaskLog {
+    name: String,
+    path: PathBuf,
+    file: Option<BufWriter<File>>,
+    /// Whether the log was written in full.
+    written: bool,
+}
+
+impl Logs {
+    /// Creates the log directory if it does not already exist.
+    pub fn new(directory: &Path, timestamps: bool, names: &[&str]) -> std::io::Result<Self> {
+        std::fs::create_dir_all(directory)?;
+
+        Ok(Logs {
+            timestamps,
+            tasks: names
+                .iter()
+                .zip(file_names(names))
+                .map(|(name, file_name)| TaskLog {
+                    name: name.to_string(),
+                    path: directory.join(file_name),
+                    file: None,
+                    written: false,
+                })
+                .collect(),
+            error: None,
+        })
+    }
+
+    /// Takes the error for the first log which could not be written, if any.
+    pub fn take_error(&mut self) -> Option<(PathBuf, std::io::Error)> {
+        self.error.take()
+    }
+
+    /// The path of the log for the given task, if one was written.
+    pub fn path(&self, task_name: &str) -> Option<&Path> {
+        self.tasks
+            .iter()
+            .find(|task| task.name == task_name && task.written)
+            .map(|task| task.path.as_path())
+    }
+
+    /// Writes a line as the task wrote it, even if it is not valid UTF-8.
+    fn write_line(&mut self, task_id: usize, line: &[u8]) {
+        let timestamps = self.timestamps;
+        let Some(file) = &mut self.tasks[task_id].file else {
+            return;
+        };
+
+        let result = (|| {
+            if timestamps {
+                write!(
+                    file,
+                    "{} ",
+                    humantime::format_rfc3339_millis(SystemTime::now())
+                )?;
+            }
+
+            file.write_all(line)?;
+            file.write_all(b"\n")
+        })();
+
+        self.check(task_id, result);
+    }
+
+    /// Stops writing a task’s log if there was an error, keeping the first error.
+    fn check(&mut self, task_id: usize, result: std::io::Result<()>) {
+        if let Err(e) = result {
+            let task = &mut self.tasks[task_id];
+            task.file = None;
+            task.written = false;
+            self.error.get_or_insert_with(|| (task.path.clone(), e));
+        }
+    }
+}
+
+/// Produces a file name for each task, replacing characters that cannot appear in paths.
+/// Tasks whose names would then be the same, ignoring case, are given a numbered suffix.
+pub(crate) fn file_names(task_names: &[&str]) -> Vec<String> {
+    let mut used = BTreeSet::new();
+    task_names
+        .iter()
+        .map(|task_name| {
+            let safe: String = task_name
+                .chars()
+                .map(|c| match c {
+                    '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+                    c if c.is_control() => '_',
+                    c => c,
+                })
+                .collect();
+
+            let mut file_name = format!("{safe}.log");
+            let mut suffix = 1;
+            while !used.insert(file_name.to_lowercase()) {
+                suffix += 1;
+                file_name = format!("{safe}-{suffix}.log");
+            }
+
+            file_name
+        })
+        .collect()
+}
+
+impl crate::output::Output<StatusMessage> for Logs {
+    fn handle(&mut self, (task_id, status): StatusMessage) {
+        match status {
+            TaskStatus::Started { .. } => {
+                let task = &mut self.tasks[task_id];
+                // any previous log is replaced
+                let result = File::create(&task.path).map(|file| {
+                    task.file = Some(BufWriter::new(file));
+                    task.written = true;
+                });
+
+                self.check(task_id, result);
+            }
+            TaskStatus::StdOut(line) | TaskStatus::StdErr(line) | TaskStatus::Combined(line) => {
+                self.write_line(task_id, line.as_bytes())
+            }
+            // only the final state of each line is logged
+            TaskStatus::Progress(_) => {}
+            TaskStatus::Finished { .. } => {
+                if let Some(mut file) = self.tasks[task_id].file.take() {
+                    let result = file.flush();
+                    self.check(task_id, result);
+                }
+            }
+        }
+    }
+}
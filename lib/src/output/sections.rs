@@ -0,0 +1,253 @@
+use std::time::SystemTime;
+
+use crate::{
+    commands::Message,
+    matcher::Diagnostic,
+    nurfile::CiProvider,
+    output::{github, highlight::Severity},
+    StatusMessage, TaskError, TaskResult, TaskStatus,
+};
+
+/// Writes the output of each task as a collapsible section, in the syntax of a CI provider.
+///
+/// Output is held until a task finishes, so that sections of failed tasks
+/// can be left expanded where the provider supports it.
+pub struct Sections<O> {
+    output: O,
+    provider: CiProvider,
+    names: Vec<String>,
+    tasks: Vec<Section>,
+}
+
+#[derive(Default)]
+struct Section {
+    started: Option<SystemTime>,
+    lines: Vec<Message>,
+}
+
+impl<O> Sections<O> {
+    pub fn new(output: O, provider: CiProvider, names: Vec<String>) -> Self {
+        Sections {
+            output,
+            provider,
+            tasks: names.iter().map(|_| Section::default()).collect(),
+            names,
+        }
+    }
+}
+
+fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Escapes a value for use in an Azure Pipelines logging command.
+fn azure_escape(s: &str) -> String {
+    s.replace('%', "%AZP25")
+        .replace(';', "%3B")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+        .replace(']', "%5D")
+}
+
+/// Escapes a value for use in a TeamCity service message.
+fn teamcity_escape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '|' | '\'' | '[' | ']' => {
+                result.push('|');
+                result.push(c);
+            }
+            '\n' => result.push_str("|n"),
+            '\r' => result.push_str("|r"),
+            c => result.push(c),
+        }
+    }
+
+    result
+}
+
+impl CiProvider {
+    /// The lines which begin and end a section.
+    fn section_markers(
+        self,
+        task_id: usize,
+        name: &str,
+        started: SystemTime,
+        finished: SystemTime,
+        failed: bool,
+    ) -> (String, Option<String>) {
+        match self {
+            CiProvider::GitHubActions => (format!("::group::{name}"), Some("::endgroup::".into())),
+            CiProvider::GitLab => {
+                // section names may only contain letters, digits, and -._
+                let id: String = name
+                    .chars()
+                    .map(|c| match c {
+                        'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '.' | '_' => c,
+                        _ => '_',
+                    })
+                    .collect();
+                let id = format!("nur_{task_id}_{id}");
+
+                (
+                    format!(
+                        "\x1b[0Ksection_start:{}:{id}[collapsed={}]\r\x1b[0K{name}",
+                        unix_time(started),
+                        !failed
+                    ),
+                    Some(format!(
+                        "\x1b[0Ksection_end:{}:{id}\r\x1b[0K",
+                        unix_time(finished)
+                    )),
+                )
+            }
+            CiProvider::AzurePipelines => (format!("##[group]{name}"), Some("##[endgroup]".into())),
+            // Buildkite sections run until the next one starts;
+            // `+++` starts a section which is expanded
+            CiProvider::Buildkite => (
+                format!("{} {name}", if failed { "+++" } else { "---" }),
+                None,
+            ),
+            CiProvider::TeamCity => {
+                let name = teamcity_escape(name);
+                (
+                    format!("##teamcity[blockOpened name='{name}']"),
+                    Some(format!("##teamcity[blockClosed name='{name}']")),
+                )
+            }
+        }
+    }
+
+    /// The line reporting a failed task.
+    fn failure_line(self, name: &str, error: &TaskError) -> String {
+        let message = format!("Task ‘{name}’ failed: {error}");
+        match self {
+            CiProvider::AzurePipelines => format!("##[error]{message}"),
+            CiProvider::TeamCity => format!(
+                "##teamcity[buildProblem description='{}']",
+                teamcity_escape(&message)
+            ),
+            CiProvider::GitHubActions | CiProvider::GitLab | CiProvider::Buildkite => message,
+        }
+    }
+
+    /// The line which attaches a diagnostic to its source, where the provider supports it.
+    fn annotation(self, diagnostic: &Diagnostic) -> Option<String> {
+        let file = diagnostic.file.to_string_lossy();
+        match self {
+            CiProvider::GitHubActions => {
+                let command = match diagnostic.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                    Severity::Note => "notice",
+                    Severity::Ignore => return None,
+                };
+
+                let mut properties = vec![
+                    format!("file={}", github::escape_property(&file)),
+                    format!("line={}", diagnostic.line),
+                ];
+                if let Some(column) = diagnostic.column {
+                    properties.push(format!("col={column}"));
+                }
+
+                Some(format!(
+                    "::{command} {}::{}",
+                    properties.join(","),
+                    github::escape_data(&diagnostic.message)
+                ))
+            }
+            CiProvider::AzurePipelines => {
+                let kind = match diagnostic.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                    Severity::Note | Severity::Ignore => return None,
+                };
+
+                let mut properties = vec![
+                    format!("type={kind}"),
+                    format!("sourcepath={}", azure_escape(&file)),
+                    format!("linenumber={}", diagnostic.line),
+                ];
+                if let Some(column) = diagnostic.column {
+                    properties.push(format!("columnnumber={column}"));
+                }
+
+                Some(format!(
+                    "##vso[task.logissue {};]{}",
+                    properties.join(";"),
+                    azure_escape(&diagnostic.message)
+                ))
+            }
+            CiProvider::GitLab | CiProvider::Buildkite | CiProvider::TeamCity => None,
+        }
+    }
+}
+
+impl<O: crate::output::Output<Message>> crate::output::Output<StatusMessage> for Sections<O> {
+    fn handle(&mut self, (task_id, status): StatusMessage) {
+        let section = &mut self.tasks[task_id];
+        match status {
+            TaskStatus::StdOut(line) | TaskStatus::Combined(line) => {
+                section.lines.push(Message::Out(line.into_string()))
+            }
+            TaskStatus::StdErr(line) => section.lines.push(Message::Err(line.into_string())),
+            TaskStatus::Progress(_) => {}
+            TaskStatus::Started { time } => section.started = Some(time),
+            TaskStatus::Finished {
+                result,
+                time,
+                diagnostics,
+                ..
+            } => self.write_section(task_id, result, time, &diagnostics),
+        }
+    }
+}
+
+impl<O: crate::output::Output<Message>> Sections<O> {
+    fn write_section(
+        &mut self,
+        task_id: usize,
+        result: Result<TaskResult, TaskError>,
+        finished: SystemTime,
+        diagnostics: &[Diagnostic],
+    ) {
+        let section = std::mem::take(&mut self.tasks[task_id]);
+        let name = &self.names[task_id];
+
+        // tasks which never started have no section, only a status line
+        if let Some(started) = section.started {
+            let (start, end) =
+                self.provider
+                    .section_markers(task_id, name, started, finished, result.is_err());
+
+            self.output.handle(Message::Out(start));
+            for line in section.lines {
+                self.output.handle(line);
+            }
+
+            if let Some(end) = end {
+                self.output.handle(Message::Out(end));
+            }
+        }
+
+        for annotation in diagnostics
+            .iter()
+            .filter_map(|d| self.provider.annotation(d))
+        {
+            self.output.handle(Message::Out(annotation));
+        }
+
+        let status = match result {
+            Ok(TaskResult::RanToCompletion) => format!("Task ‘{name}’ completed"),
+            Ok(TaskResult::Skipped) => format!("Task ‘{name}’ skipped"),
+            Ok(TaskResult::Cancelled) => format!("Task ‘{name}’ cancelled"),
+            Err(e) => self.provider.failure_line(name, &e),
+        };
+
+        self.output.handle(Message::Out(status));
+    }
+}
@@ -18,6 +18,11 @@ impl<'a> crate::output::Output<Message> for Sink<'a> {
                 line.push('\n');
                 _ = self.stderr.write_all(line.as_bytes());
             }
+            Message::Progress(line) => {
+                // return to the start of the line, and clear whatever follows
+                _ = write!(self.stdout, "\r{line}\x1b[K");
+                _ = self.stdout.flush();
+            }
         }
     }
 }
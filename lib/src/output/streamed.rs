@@ -1,4 +1,16 @@
-use crate::{commands::Message, StatusMessage, TaskResult, TaskStatus};
+use owo_colors::OwoColorize;
+
+use std::time::SystemTime;
+
+use crate::{
+    commands::Message,
+    output::{
+        highlight::Highlighter,
+        template::{Templates, Values},
+        Styles,
+    },
+    Line, StatusMessage, TaskError, TaskResult, TaskStatus,
+};
 
 pub struct Streamed<O> {
     last_id: usize,
@@ -9,6 +21,18 @@ pub struct Streamed<O> {
     separator_last: String,
     names: Vec<String>,
     prefixer: Box<dyn crate::output::Prefixer>,
+    /// Prefixers for tasks which override the prefix style.
+    task_prefixers: Vec<Option<Box<dyn crate::output::Prefixer>>>,
+    styles: Styles,
+    /// The styles of lines written to stderr, which may not be a terminal when stdout is.
+    stderr_styles: Styles,
+    templates: Templates,
+    started: Vec<Option<SystemTime>>,
+    highlighter: Highlighter,
+    /// Whether lines which are being overwritten are shown in place.
+    in_place: bool,
+    /// Whether the last line written was a progress line, which must be cleared.
+    showing_progress: bool,
 }
 
 impl<O> Streamed<O> {
@@ -23,6 +47,10 @@ impl<O> Streamed<O> {
     ) -> Self {
         Streamed {
             last_id: usize::MAX,
+            started: vec![None; names.len()],
+            highlighter: Highlighter::default(),
+            in_place: false,
+            showing_progress: false,
             output,
             separator,
             separator_first,
@@ -30,6 +58,68 @@ impl<O> Streamed<O> {
             separator_last,
             names,
             prefixer,
+            task_prefixers: Vec::new(),
+            styles: Styles::default(),
+            stderr_styles: Styles::default(),
+            templates: Templates::default(),
+        }
+    }
+
+    /// Sets the styles used for task prefixes and status lines,
+    /// and those used for lines which are written to stderr.
+    pub fn with_styles(self, styles: Styles, stderr_styles: Styles) -> Self {
+        Streamed {
+            styles,
+            stderr_styles,
+            ..self
+        }
+    }
+
+    /// Sets the templates used for prefixes and status lines.
+    pub fn with_templates(self, templates: Templates) -> Self {
+        Streamed { templates, ..self }
+    }
+
+    /// Sets the prefixers used for individual tasks, in place of the main prefixer.
+    pub fn with_task_prefixers(
+        self,
+        task_prefixers: Vec<Option<Box<dyn crate::output::Prefixer>>>,
+    ) -> Self {
+        Streamed {
+            task_prefixers,
+            ..self
+        }
+    }
+
+    /// Shows the intermediate states of lines which are overwritten using
+    /// carriage returns, such as progress bars, in place. This needs a terminal.
+    pub fn with_progress(self, in_place: bool) -> Self {
+        Streamed { in_place, ..self }
+    }
+
+    /// Sets the rules used to highlight warnings and errors in task output.
+    pub fn with_highlighter(self, highlighter: Highlighter) -> Self {
+        Streamed {
+            highlighter,
+            ..self
+        }
+    }
+
+    /// Styles a line of output according to its severity.
+    fn highlight(&self, line: Line, stderr: bool) -> Line {
+        let severity = self.highlighter.severity(&line);
+        let styles = if stderr {
+            &self.stderr_styles
+        } else {
+            &self.styles
+        };
+
+        match severity {
+            Some(severity) => line
+                .style(styles.severity(Some(severity)))
+                .to_string()
+                .into(),
+            None => line,
         }
     }
 }
@@ -38,46 +128,134 @@ impl<O: crate::output::Output<Message>> crate::output::Output<StatusMessage> for
     fn handle(&mut self, msg: crate::StatusMessage) {
         let (task_id, status) = msg;
 
+        if let TaskStatus::Progress(_) = status {
+            if !self.in_place {
+                return;
+            }
+        } else if self.showing_progress {
+            // the progress line is replaced by whatever comes next
+            self.output.handle(Message::Progress(String::new()));
+            self.showing_progress = false;
+        }
+
+        let status = match status {
+            TaskStatus::StdOut(line) => TaskStatus::StdOut(self.highlight(line, false)),
+            TaskStatus::StdErr(line) => TaskStatus::StdErr(self.highlight(line, true)),
+            TaskStatus::Combined(line) => TaskStatus::Combined(self.highlight(line, false)),
+            status => status,
+        };
+
         let name = &self.names[task_id];
-        let prefix = self.prefixer.prefix(name);
+        let style = self.styles.task(task_id);
         let sep = if task_id == self.last_id {
             &self.separator
         } else {
             &self.separator_switch
         };
 
+        let prefixer = match self.task_prefixers.get_mut(task_id) {
+            Some(Some(prefixer)) => prefixer,
+            _ => &mut self.prefixer,
+        };
+
+        if task_id != self.last_id {
+            prefixer.reset();
+        }
+
+        let mut values = Values {
+            name,
+            timestamp: Some(SystemTime::now()),
+            ..Default::default()
+        };
+
+        let mut prefix = |values: &Values| match &self.templates.prefix {
+            Some(template) => template.render(values),
+            None => prefixer.prefix(name).to_string(),
+        };
+
         let to_send = match status {
             TaskStatus::StdOut(line) => {
-                let line = format!("{prefix}{}{line}", sep);
+                values.stream = Some("stdout");
+                let line = format!("{}{line}", format!("{}{sep}", prefix(&values)).style(style));
                 Message::Out(line)
             }
             TaskStatus::StdErr(line) => {
-                let line = format!("{prefix}{}{line}", sep);
+                values.stream = Some("stderr");
+                let style = self.stderr_styles.task(task_id);
+                let line = format!("{}{line}", format!("{}{sep}", prefix(&values)).style(style));
                 Message::Err(line)
             }
-            TaskStatus::Started {} => {
-                let line = format!("{prefix}{}╴ Started task ‘{name}’", self.separator_first);
+            TaskStatus::Combined(line) => {
+                values.stream = Some("combined");
+                let line = format!("{}{line}", format!("{}{sep}", prefix(&values)).style(style));
                 Message::Out(line)
             }
-            TaskStatus::Finished { result } => {
-                let msg = match result {
-                    Ok(TaskResult::Skipped) => {
-                        format!("{prefix}{}╴ Task ‘{name}’ skipped", self.separator_last)
-                    }
+            TaskStatus::Progress(line) => {
+                let line = format!("{}{line}", format!("{}{sep}", prefix(&values)).style(style));
+                Message::Progress(line)
+            }
+            TaskStatus::Started { time } => {
+                self.started[task_id] = Some(time);
+                values.timestamp = Some(time);
+                let line = format!(
+                    "{}{}",
+                    format!("{}{}", prefix(&values), self.separator_first).style(style),
+                    self.templates.start.render(&values)
+                );
+                Message::Out(line)
+            }
+            TaskStatus::Finished {
+                result,
+                time,
+                counts,
+                ..
+            } => {
+                values.timestamp = Some(time);
+                values.elapsed =
+                    self.started[task_id].and_then(|started| time.duration_since(started).ok());
+
+                let (template, status_style) = match &result {
+                    Ok(TaskResult::Skipped) => (&self.templates.skip, self.styles.skipped),
                     Ok(TaskResult::RanToCompletion) => {
-                        format!("{prefix}{}╴ Task ‘{name}’ completed", self.separator_last)
+                        (&self.templates.finish, self.styles.success)
                     }
-                    Ok(TaskResult::Cancelled) => {
-                        format!("{prefix}{}╴ Task ‘{name}’ cancelled", self.separator_last)
+                    Ok(TaskResult::Cancelled) => (&self.templates.cancel, self.styles.skipped),
+                    Err(e) => {
+                        values.error = Some(e.to_string());
+                        if let TaskError::Failed { exit_status, .. } = e {
+                            values.exit_code = Some(match exit_status.code() {
+                                Some(code) => code.to_string(),
+                                None => exit_status.to_string(),
+                            });
+                        }
+
+                        (&self.templates.failure, self.styles.failure)
                     }
-                    Err(r) => format!("{prefix}{}╴ Task ‘{name}’ failed: {r}", self.separator_last),
                 };
 
-                Message::Out(msg)
+                let mut status_line = template.render(&values);
+                if !counts.is_empty() {
+                    status_line.push_str(&format!(" ({counts})"));
+                }
+
+                Message::Out(format!(
+                    "{}{}",
+                    format!("{}{}", prefix(&values), self.separator_last).style(style),
+                    status_line.style(status_style)
+                ))
             }
         };
 
-        self.last_id = task_id;
+        if let Message::Progress(_) = to_send {
+            // the progress line will be cleared, so the next line
+            // should be prefixed as if it had not been there
+            self.showing_progress = true;
+            if task_id != self.last_id {
+                prefixer.reset();
+            }
+        } else {
+            self.last_id = task_id;
+        }
 
         self.output.handle(to_send);
     }
@@ -0,0 +1,174 @@
+use std::{io::Write, time::SystemTime};
+
+use crate::{
+    output::{format_duration, highlight::Counts},
+    StatusMessage, TaskError, TaskResult, TaskStatus,
+};
+
+/// Collects the outcome of each task, to be summarized after all have finished.
+pub struct Summary {
+    started: SystemTime,
+    tasks: Vec<TaskSummary>,
+}
+
+struct TaskSummary {
+    name: String,
+    started: Option<SystemTime>,
+    finished: Option<(SystemTime, Result<TaskResult, TaskError>)>,
+    counts: Counts,
+}
+
+impl TaskSummary {
+    fn status(&self) -> &'static str {
+        match &self.finished {
+            None => "unfinished",
+            Some((_, Ok(TaskResult::RanToCompletion))) => "completed",
+            Some((_, Ok(TaskResult::Skipped))) => "skipped",
+            Some((_, Ok(TaskResult::Cancelled))) => "cancelled",
+            Some((_, Err(_))) => "failed",
+        }
+    }
+
+    fn failed(&self) -> bool {
+        matches!(self.finished, Some((_, Err(_))))
+    }
+
+    fn wall_time(&self) -> Option<String> {
+        let started = self.started?;
+        let (finished, _) = self.finished.as_ref()?;
+        let elapsed = finished.duration_since(started).unwrap_or_default();
+        Some(format_duration(elapsed))
+    }
+
+    fn exit_code(&self) -> Option<String> {
+        match &self.finished {
+            Some((_, Err(TaskError::Failed { exit_status, .. }))) => {
+                Some(match exit_status.code() {
+                    Some(code) => code.to_string(),
+                    None => exit_status.to_string(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Summary {
+    pub fn new(names: Vec<String>) -> Self {
+        Summary {
+            started: SystemTime::now(),
+            tasks: names
+                .into_iter()
+                .map(|name| TaskSummary {
+                    name,
+                    started: None,
+                    finished: None,
+                    counts: Counts::default(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Writes a table of each task’s outcome, with any failures listed last.
+    pub fn write(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        let mut tasks: Vec<&TaskSummary> = self.tasks.iter().collect();
+        tasks.sort_by_key(|task| task.failed());
+
+        let name_width = tasks.iter().map(|t| t.name.len()).max().unwrap_or_default();
+        let rows: Vec<(&TaskSummary, String)> = tasks
+            .into_iter()
+            .map(|task| (task, task.wall_time().unwrap_or_else(|| "-".to_string())))
+            .collect();
+        let time_width = rows
+            .iter()
+            .map(|(_, time)| time.len())
+            .max()
+            .unwrap_or_default();
+
+        writeln!(out)?;
+        writeln!(out, "Summary:")?;
+        for (task, time) in &rows {
+            let line = format!(
+                "  {:<name_width$}  {:<10}  {:>time_width$}",
+                task.name,
+                task.status(),
+                time,
+            );
+
+            let mut details = Vec::new();
+            if let Some(code) = task.exit_code() {
+                details.push(format!("exit {code}"));
+            }
+
+            if !task.counts.is_empty() {
+                details.push(task.counts.to_string());
+            }
+
+            if details.is_empty() {
+                writeln!(out, "{}", line.trim_end())?;
+            } else {
+                writeln!(out, "{line}  {}", details.join("  "))?;
+            }
+        }
+
+        let mut counts: Vec<(&str, usize)> = Vec::new();
+        for (task, _) in &rows {
+            match counts
+                .iter_mut()
+                .find(|(status, _)| *status == task.status())
+            {
+                Some((_, count)) => *count += 1,
+                None => counts.push((task.status(), 1)),
+            }
+        }
+
+        let counts: Vec<String> = counts
+            .into_iter()
+            .map(|(status, count)| format!("{count} {status}"))
+            .collect();
+
+        let mut diagnostics = Counts::default();
+        for task in &self.tasks {
+            diagnostics += task.counts;
+        }
+
+        let diagnostics = if diagnostics.is_empty() {
+            String::new()
+        } else {
+            format!("; {diagnostics}")
+        };
+
+        let elapsed = SystemTime::now()
+            .duration_since(self.started)
+            .unwrap_or_default();
+
+        writeln!(
+            out,
+            "{} {} in {}: {}{diagnostics}",
+            rows.len(),
+            if rows.len() == 1 { "task" } else { "tasks" },
+            format_duration(elapsed),
+            counts.join(", ")
+        )
+    }
+}
+
+impl crate::output::Output<StatusMessage> for Summary {
+    fn handle(&mut self, (task_id, status): StatusMessage) {
+        let task = &mut self.tasks[task_id];
+        match status {
+            TaskStatus::Progress(_) => {}
+            TaskStatus::Started { time } => task.started = Some(time),
+            TaskStatus::Finished {
+                result,
+                time,
+                counts,
+                ..
+            } => {
+                task.finished = Some((time, result));
+                task.counts = counts;
+            }
+            TaskStatus::StdOut(_) | TaskStatus::StdErr(_) | TaskStatus::Combined(_) => {}
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use std::{
+    fmt::Write,
+    time::{Duration, SystemTime},
+};
+
+use crate::output::format_duration;
+
+/// A line of output with placeholders, such as `Task ‘{name}’ completed in {elapsed}`.
+///
+/// Literal braces are written as `{{` and `}}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placeholder {
+    Name,
+    Elapsed,
+    Timestamp,
+    ExitCode,
+    Stream,
+    Error,
+}
+
+impl Placeholder {
+    const ALL: &'static [(&'static str, Placeholder)] = &[
+        ("name", Placeholder::Name),
+        ("elapsed", Placeholder::Elapsed),
+        ("timestamp", Placeholder::Timestamp),
+        ("exit_code", Placeholder::ExitCode),
+        ("stream", Placeholder::Stream),
+        ("error", Placeholder::Error),
+    ];
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum TemplateError {
+    #[error(
+        "unknown placeholder ‘{{{name}}}’ (expected one of: {})",
+        known_placeholders()
+    )]
+    UnknownPlaceholder { name: String },
+
+    #[error("unclosed ‘{{’ (write ‘{{{{’ for a literal brace)")]
+    Unclosed,
+
+    #[error("unmatched ‘}}’ (write ‘}}}}’ for a literal brace)")]
+    Unmatched,
+}
+
+fn known_placeholders() -> String {
+    Placeholder::ALL
+        .iter()
+        .map(|(name, _)| format!("{{{name}}}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl std::str::FromStr for Template {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '}' => return Err(TemplateError::Unmatched),
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(TemplateError::Unclosed),
+                        }
+                    }
+
+                    let placeholder = Placeholder::ALL
+                        .iter()
+                        .find(|(n, _)| *n == name.trim())
+                        .map(|(_, p)| *p)
+                        .ok_or(TemplateError::UnknownPlaceholder { name })?;
+
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+
+                    segments.push(Segment::Placeholder(placeholder));
+                }
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Template { segments })
+    }
+}
+
+/// The values available to a template. Missing values are rendered as empty.
+#[derive(Default)]
+pub struct Values<'a> {
+    pub name: &'a str,
+    pub elapsed: Option<Duration>,
+    pub timestamp: Option<SystemTime>,
+    pub exit_code: Option<String>,
+    pub stream: Option<&'a str>,
+    pub error: Option<String>,
+}
+
+impl Template {
+    fn new(s: &str) -> Self {
+        s.parse().expect("built-in templates are valid")
+    }
+
+    pub fn render(&self, values: &Values) -> String {
+        let mut result = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(s) => result.push_str(s),
+                Segment::Placeholder(p) => match p {
+                    Placeholder::Name => result.push_str(values.name),
+                    Placeholder::Elapsed => {
+                        if let Some(elapsed) = values.elapsed {
+                            result.push_str(&format_duration(elapsed));
+                        }
+                    }
+                    Placeholder::Timestamp => {
+                        if let Some(timestamp) = values.timestamp {
+                            _ = write!(result, "{}", humantime::format_rfc3339_seconds(timestamp));
+                        }
+                    }
+                    Placeholder::ExitCode => {
+                        result.push_str(values.exit_code.as_deref().unwrap_or_default())
+                    }
+                    Placeholder::Stream => result.push_str(values.stream.unwrap_or_default()),
+                    Placeholder::Error => {
+                        result.push_str(values.error.as_deref().unwrap_or_default())
+                    }
+                },
+            }
+        }
+
+        result
+    }
+}
+
+/// The templates for each line written about a task.
+#[derive(Debug, Clone)]
+pub struct Templates {
+    /// Replaces the prefix style, if set.
+    pub prefix: Option<Template>,
+    pub start: Template,
+    pub finish: Template,
+    pub skip: Template,
+    pub cancel: Template,
+    pub failure: Template,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Templates {
+            prefix: None,
+            start: Template::new("╴ Started task ‘{name}’"),
+            finish: Template::new("╴ Task ‘{name}’ completed"),
+            skip: Template::new("╴ Task ‘{name}’ skipped"),
+            cancel: Template::new("╴ Task ‘{name}’ cancelled"),
+            failure: Template::new("╴ Task ‘{name}’ failed: {error}"),
+        }
+    }
+}
+
+impl Templates {
+    /// Templates which use only ASCII characters, for terminals without Unicode support.
+    pub fn ascii() -> Self {
+        Templates {
+            prefix: None,
+            start: Template::new("- Started task '{name}'"),
+            finish: Template::new("- Task '{name}' completed"),
+            skip: Template::new("- Task '{name}' skipped"),
+            cancel: Template::new("- Task '{name}' cancelled"),
+            failure: Template::new("- Task '{name}' failed: {error}"),
+        }
+    }
+}
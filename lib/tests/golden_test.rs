@@ -2,15 +2,16 @@ use std::{io::Write, path::Path};
 
 use miette::{MietteHandlerOpts, Result};
 
-use nur_lib::{commands::Command, nurfile::OutputOptions};
-
-#[test]
-fn check() -> Result<()> {
-    // https://www.youtube.com/watch?v=jTqwe57ObFo
+use nur_lib::{
+    commands::{Command, Format, Graph, GraphFormat, List, Task},
+    nurfile::{CiProvider, OutputOptions},
+    output::ColorChoice,
+};
 
+fn set_hook() {
     // override default hook,  since we need the output
     // to be consistent regardless of where it is running
-    miette::set_hook(Box::new(|_diag| {
+    _ = miette::set_hook(Box::new(|_diag| {
         Box::new(
             MietteHandlerOpts::new()
                 .terminal_links(false)
@@ -19,7 +20,13 @@ fn check() -> Result<()> {
                 .width(132)
                 .build(),
         )
-    }))?;
+    }));
+}
+
+#[test]
+fn check() {
+    // https://www.youtube.com/watch?v=jTqwe57ObFo
+    set_hook();
 
     // normalize paths to avoid spurious changes
     insta::with_settings!({filters => vec![("[^\"\\[]+\\.yml", "[…].yml")]}, {
@@ -27,16 +34,636 @@ fn check() -> Result<()> {
             let mut output_buf = Vec::new();
             let mut error_buf = Vec::new();
 
-            let result = run_config(path, &mut output_buf, &mut error_buf);
+            let result = run_config(task_for(path), path, &mut output_buf, &mut error_buf);
+            let golden = prep_output(&output_buf, &error_buf, result);
+            insta::assert_snapshot!(golden);
+        });
+    });
+}
+
+#[test]
+fn dry_run() {
+    set_hook();
+
+    insta::with_settings!({filters => vec![
+        ("[^\"\\[]+\\.yml", "[…].yml"),
+        ("\"[^\"]*test_inputs\"", "\"[…]\""),
+    ]}, {
+        insta::glob!("test_inputs/*.yml", |path| {
+            let mut output_buf = Vec::new();
+            let mut error_buf = Vec::new();
+
+            let task_command = Task {
+                dry_run: true,
+                ..task_for(path)
+            };
+
+            let result = run_config(task_command, path, &mut output_buf, &mut error_buf);
+            let golden = prep_output(&output_buf, &error_buf, result);
+            insta::assert_snapshot!(golden);
+        });
+    });
+}
+
+#[test]
+fn dry_run_json() {
+    check_one("dry_run_json", "arguments.yml", |task| Task {
+        dry_run: true,
+        format: Format::Json,
+        args: vec!["one".to_string(), "two words".to_string()],
+        ..task
+    });
+}
+
+#[test]
+fn graph() {
+    set_hook();
+
+    insta::with_settings!({filters => vec![("[^\"\\[]+\\.yml", "[…].yml")]}, {
+        insta::glob!("test_inputs/*.yml", |path| {
+            let mut output_buf = Vec::new();
+            let mut error_buf = Vec::new();
+
+            let result = [GraphFormat::Dot, GraphFormat::Mermaid]
+                .into_iter()
+                .try_for_each(|format| {
+                    let graph_command = Graph {
+                        nur_file: Some(path.to_owned()),
+                        task_names: Vec::new(),
+                        format,
+                    };
+
+                    let ctx = nur_lib::commands::Context {
+                        cwd: path.parent().unwrap().to_owned(),
+                        stdout: &mut output_buf,
+                        stderr: &mut error_buf,
+                    };
+
+                    graph_command.run(ctx)
+                });
+
             let golden = prep_output(&output_buf, &error_buf, result);
             insta::assert_snapshot!(golden);
         });
     });
+}
+
+#[test]
+fn check_json_output() {
+    check_one("check_json_output", "arguments.yml", |task| Task {
+        output_override: Some(OutputOptions {
+            style: nur_lib::nurfile::OutputStyle::Json,
+            ..Default::default()
+        }),
+        ..task
+    });
+}
 
-    Ok(())
+#[test]
+fn check_json_summary() {
+    check_one("check_json_summary", "arguments.yml", |task| Task {
+        output_override: Some(OutputOptions {
+            style: nur_lib::nurfile::OutputStyle::Json,
+            ..Default::default()
+        }),
+        summary: true,
+        ..task
+    });
+}
+
+#[test]
+fn check_json_diagnostics() {
+    check_one("check_json_diagnostics", "matchers.yml", |task| Task {
+        output_override: Some(OutputOptions {
+            style: nur_lib::nurfile::OutputStyle::Json,
+            ..Default::default()
+        }),
+        ..task
+    });
+}
+
+#[test]
+fn check_json_combined() {
+    check_one("check_json_combined", "streams.yml", |task| Task {
+        output_override: Some(OutputOptions {
+            style: nur_lib::nurfile::OutputStyle::Json,
+            ..Default::default()
+        }),
+        ..task
+    });
+}
+
+#[test]
+fn check_with_arguments() {
+    check_one("check_with_arguments", "arguments.yml", |task| Task {
+        args: vec!["one".to_string(), "two words".to_string()],
+        ..task
+    });
+}
+
+#[test]
+fn check_arguments_with_repeated_tasks() {
+    check_one(
+        "check_arguments_with_repeated_tasks",
+        "arguments.yml",
+        |task| Task {
+            task_names: ["helper", "default", "helper"].map(String::from).into(),
+            args: vec!["one".to_string()],
+            ..task
+        },
+    );
+}
+
+#[test]
+fn check_in_sequence() {
+    check_one("check_in_sequence", "sequence.yml", |task| Task {
+        task_names: vec!["build".to_string(), "then".to_string(), "clean".to_string()],
+        ..task
+    });
+}
+
+#[test]
+fn check_in_sequence_after_dependent() {
+    // default depends upon build, so build still runs first
+    check_one(
+        "check_in_sequence_after_dependent",
+        "sequence.yml",
+        |task| Task {
+            task_names: vec![
+                "default".to_string(),
+                "then".to_string(),
+                "build".to_string(),
+            ],
+            ..task
+        },
+    );
+}
+
+#[test]
+fn check_serial() {
+    check_one("check_serial", "sequence.yml", |task| Task {
+        task_names: vec!["clean".to_string(), "build".to_string()],
+        serial: true,
+        ..task
+    });
+}
+
+#[test]
+fn check_summary() {
+    check_one("check_summary", "multiple_failures.yml", |task| Task {
+        summary: true,
+        ..task
+    });
+}
+
+#[test]
+fn check_highlight_summary() {
+    check_one("check_highlight_summary", "highlight.yml", |task| Task {
+        summary: true,
+        ..task
+    });
+}
+
+#[test]
+fn check_highlight_filtered() {
+    // the status lines and the summary count the lines which are not shown
+    check_one(
+        "check_highlight_filtered",
+        "highlight_filtered.yml",
+        |task| Task {
+            output_override: None,
+            summary: true,
+            ..task
+        },
+    );
+}
+
+#[test]
+fn check_color() {
+    // task prefixes, highlighted lines, and status lines on both streams
+    check_one("check_color", "highlight.yml", |task| Task {
+        color: ColorChoice::Always,
+        ..task
+    });
+}
+
+#[test]
+fn check_junit_report() {
+    let report_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("junit.xml");
+    check_one("check_junit_report", "junit.yml", |task| Task {
+        junit_report: Some(report_path.clone()),
+        ..task
+    });
+
+    let report = std::fs::read_to_string(&report_path).unwrap();
+    insta::with_settings!({filters => vec![
+        ("time=\"[0-9.]+\"", "time=\"[…]\""),
+        ("timestamp=\"[^\"]+\"", "timestamp=\"[…]\""),
+    ]}, {
+        insta::assert_snapshot!("junit_report", report);
+    });
+}
+
+#[test]
+fn check_task_logs() {
+    let log_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("logs");
+    check_one("check_task_logs", "junit.yml", |task| Task {
+        log_dir: Some(log_dir.clone()),
+        ..task
+    });
+
+    let log = std::fs::read_to_string(log_dir.join("report.log")).unwrap();
+    insta::assert_snapshot!("task_log", log);
+}
+
+#[test]
+fn check_raw_logs() {
+    let log_dir = Path::new(env!("CARGO_TARGET_TMPDIR"))
+        .join("bytes")
+        .join("logs");
+    check_one("check_raw_logs", "bytes.yml", |task| Task {
+        log_dir: Some(log_dir.clone()),
+        ..task
+    });
+
+    // logs keep the output exactly as it was written
+    let log = std::fs::read(log_dir.join("default.log")).unwrap();
+    assert_eq!(log, b"caf\xe9\nafter\nno newline\n");
+}
+
+#[test]
+fn check_log_name_collisions() {
+    let log_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("log_names");
+    _ = std::fs::remove_dir_all(&log_dir);
+    check_one("check_log_name_collisions", "log_names.yml", |task| Task {
+        log_dir: Some(log_dir.clone()),
+        ..task
+    });
+
+    // each task has its own log, rather than overwriting another's
+    let mut logs: Vec<String> = std::fs::read_dir(&log_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.file_name().unwrap() != "default.log")
+        .map(|path| std::fs::read_to_string(path).unwrap())
+        .collect();
+
+    logs.sort();
+    assert_eq!(logs, ["slash\n", "underscore\n", "uppercase\n"]);
+}
+
+#[test]
+fn check_log_write_error() {
+    let log_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("unwritable_logs");
+    _ = std::fs::remove_dir_all(&log_dir);
+
+    // a directory is in the way of the log file
+    std::fs::create_dir_all(log_dir.join("default.log")).unwrap();
+    check_one("check_log_write_error", "hello_world.yml", |task| Task {
+        log_dir: Some(log_dir.clone()),
+        ..task
+    });
+}
+
+#[test]
+fn check_failure_tail() {
+    // use the output options from the file
+    let log_dir = Path::new(env!("CARGO_TARGET_TMPDIR"))
+        .join("tail")
+        .join("logs");
+    check_one("check_failure_tail", "failure_tail.yml", |task| Task {
+        output_override: None,
+        log_dir: Some(log_dir),
+        ..task
+    });
+}
+
+#[test]
+fn check_failure_tail_shrinking() {
+    // use the output options from the file
+    check_one(
+        "check_failure_tail_shrinking",
+        "failure_tail_shrinking.yml",
+        |task| Task {
+            output_override: None,
+            ..task
+        },
+    );
+}
+
+#[test]
+fn check_ci_sections() {
+    for (name, provider) in [
+        ("github", CiProvider::GitHubActions),
+        ("gitlab", CiProvider::GitLab),
+        ("azure", CiProvider::AzurePipelines),
+        ("buildkite", CiProvider::Buildkite),
+        ("teamcity", CiProvider::TeamCity),
+    ] {
+        check_one(&format!("check_ci_{name}"), "junit.yml", |task| Task {
+            output_override: Some(OutputOptions::ci(provider)),
+            ..task
+        });
+    }
+}
+
+#[test]
+fn check_annotation_lines() {
+    check_one("check_annotation_lines", "annotation_lines.yml", |task| {
+        Task {
+            output_override: Some(OutputOptions::ci(CiProvider::GitHubActions)),
+            ..task
+        }
+    });
+}
+
+#[test]
+fn check_ci_task_output() {
+    // tasks which override their output mode, in CI sections
+    check_one("check_ci_task_output", "task_output.yml", |task| Task {
+        output_override: Some(OutputOptions::ci(CiProvider::GitHubActions)),
+        ..task
+    });
+}
+
+#[test]
+fn check_annotation_repeated_command() {
+    check_one(
+        "check_annotation_repeated_command",
+        "repeated_command.yml",
+        |task| Task {
+            output_override: Some(OutputOptions::ci(CiProvider::GitHubActions)),
+            ..task
+        },
+    );
+}
+
+#[test]
+fn check_templates() {
+    // use the output options from the file
+    check_one("check_templates", "templates.yml", |task| Task {
+        output_override: None,
+        ..task
+    });
+}
+
+#[test]
+fn check_memory_limit() {
+    // with no memory to spare, all output goes through temporary files
+    check_one("check_memory_limit", "junit.yml", |task| Task {
+        output_override: Some(OutputOptions {
+            prefix: nur_lib::nurfile::PrefixStyle::Aligned,
+            style: nur_lib::nurfile::OutputStyle::Grouped {
+                separator: "│".to_string(),
+                separator_first: Some("╭".to_string()),
+                separator_last: Some("╰".to_string()),
+                deterministic: true,
+                only_on_failure: false,
+                memory_limit: 0,
+                max_lines: None,
+                failure_tail: None,
+            },
+            ..Default::default()
+        }),
+        ..task
+    });
+}
+
+#[test]
+fn list_json() {
+    check_list("list_json", "env.yml", |list| List {
+        format: Format::Json,
+        ..list
+    });
+}
+
+#[test]
+fn list_tree() {
+    check_list("list_tree", "dependencies.yml", |list| List {
+        tree: true,
+        ..list
+    });
+    check_list("list_tree_cycle", "extended-cycle.yml", |list| List {
+        tree: true,
+        ..list
+    });
+}
+
+#[test]
+fn list_pattern() {
+    check_list("list_pattern_glob", "dependencies.yml", |list| List {
+        pattern: Some("*o*".to_string()),
+        ..list
+    });
+    check_list("list_pattern_substring", "dependencies.yml", |list| List {
+        pattern: Some("ell".to_string()),
+        ..list
+    });
+}
+
+#[test]
+fn graph_task_names() {
+    // only `a` and what it depends upon, leaving out `b` and `default`
+    let path = input_path("dependency-failure.yml");
+    for (snapshot_name, format) in [
+        ("graph_task_names_dot", GraphFormat::Dot),
+        ("graph_task_names_mermaid", GraphFormat::Mermaid),
+    ] {
+        let graph_command = Graph {
+            nur_file: Some(path.clone()),
+            task_names: vec!["a".to_string()],
+            format,
+        };
+
+        snapshot_command(snapshot_name, &path, graph_command);
+    }
+}
+
+#[test]
+fn dashboard_ticks_while_output_streams() {
+    use nur_lib::{
+        output::{Dashboard, Output, Styles, Ticker},
+        TaskStatus,
+    };
+
+    let mut screen = Vec::new();
+    {
+        let mut dashboard = Dashboard::new(
+            &mut screen,
+            vec!["chatty".to_string()],
+            Styles::new(false, &["chatty"]),
+        );
+
+        dashboard.handle((
+            0,
+            TaskStatus::Started {
+                time: std::time::SystemTime::now(),
+            },
+        ));
+
+        // a line arrives more often than ticks are due, as the run loop would see
+        // for a task which prints continuously
+        let mut ticker = Ticker::new(std::time::Duration::from_millis(5));
+        for n in 0..100 {
+            dashboard.handle((0, TaskStatus::StdOut(format!("line {n}").into())));
+            if ticker.due() {
+                dashboard.tick();
+            }
+
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+
+    let screen = String::from_utf8(screen).unwrap();
+    let shown: Vec<&str> = screen
+        .lines()
+        .filter_map(|line| line.split_once("│ ").map(|(_, output)| output))
+        .collect();
+
+    assert!(
+        shown.len() > 1,
+        "the dashboard should be redrawn as output arrives: {screen:?}"
+    );
+
+    assert!(
+        shown.windows(2).all(|pair| pair[0] != pair[1]),
+        "each redraw should show the latest line: {shown:?}"
+    );
+}
+
+#[test]
+fn dashboard_keeps_last_lines_of_failed_task() {
+    use nur_lib::{
+        output::{Dashboard, Output, Styles},
+        TaskError, TaskStatus,
+    };
+
+    let mut screen = Vec::new();
+    {
+        let mut dashboard = Dashboard::new(
+            &mut screen,
+            vec!["noisy".to_string()],
+            Styles::new(false, &["noisy"]),
+        );
+
+        let time = std::time::SystemTime::now();
+        dashboard.handle((0, TaskStatus::Started { time }));
+        for n in 0..1500 {
+            dashboard.handle((0, TaskStatus::StdOut(format!("line {n}").into())));
+        }
+
+        let result = Err(TaskError::ExecutableError {
+            executable: "noisy".to_string(),
+            kind: std::io::ErrorKind::NotFound,
+        });
+
+        dashboard.handle((
+            0,
+            TaskStatus::Finished {
+                result,
+                time,
+                diagnostics: Vec::new(),
+                counts: Default::default(),
+            },
+        ));
+    }
+
+    let screen = String::from_utf8(screen).unwrap();
+    let shown: Vec<&str> = screen
+        .lines()
+        .skip_while(|line| !line.contains("Output of ‘noisy’"))
+        .collect();
+
+    assert_eq!(shown[1], "│… 500 earlier lines omitted");
+    assert_eq!(shown[2], "│line 500");
+    assert_eq!(shown[shown.len() - 2], "│line 1499");
+}
+
+#[test]
+fn exit_codes() {
+    for (file_name, expected) in [
+        ("hello_world.yml", None),
+        ("dependency-failure.yml", Some(100)),
+        ("killed.yml", Some(128 + 15)),
+        ("multiple_failures.yml", Some(nur_lib::exit_code::FAILURE)),
+        ("no_version.yml", Some(nur_lib::exit_code::NURFILE)),
+        ("self-cycle.yml", Some(nur_lib::exit_code::TASK_CYCLE)),
+        (
+            "missing-dependency.yml",
+            Some(nur_lib::exit_code::NO_SUCH_TASK),
+        ),
+    ] {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/test_inputs")
+            .join(file_name);
+
+        let result = run_config(task_for(&path), &path, &mut Vec::new(), &mut Vec::new());
+        let exit_code = result.err().map(|report| {
+            report
+                .downcast_ref::<nur_lib::Error>()
+                .expect("errors should be nur errors")
+                .exit_code()
+        });
+
+        assert_eq!(exit_code, expected, "exit code for {file_name}");
+    }
+}
+
+/// Runs a single input file with a customized task command.
+fn check_one(snapshot_name: &str, file_name: &str, customize: impl FnOnce(Task) -> Task) {
+    let path = input_path(file_name);
+    snapshot_command(snapshot_name, &path, customize(task_for(&path)));
+}
+
+/// Lists the tasks of a single input file.
+fn check_list(snapshot_name: &str, file_name: &str, customize: impl FnOnce(List) -> List) {
+    let path = input_path(file_name);
+    let list_command = customize(List {
+        nur_file: Some(path.clone()),
+        pattern: None,
+        format: Format::Text,
+        tree: false,
+        color: ColorChoice::Never,
+    });
+
+    snapshot_command(snapshot_name, &path, list_command);
+}
+
+fn input_path(file_name: &str) -> std::path::PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/test_inputs")
+        .join(file_name)
+}
+
+fn snapshot_command(snapshot_name: &str, nurfile_path: &Path, command: impl Command) {
+    set_hook();
+
+    insta::with_settings!({filters => vec![
+        ("[^\"\\[\\s=]+\\.yml", "[…].yml"),
+        ("\"[^\"]*test_inputs\"", "\"[…]\""),
+        ("\"timestamp\":\"[^\"]+\"", "\"timestamp\":\"[…]\""),
+        ("\\b\\d+\\.\\ds\\b", "[…]s"),
+        ("in \\S+/logs/", "in […]/logs/"),
+        ("\"/[^\"]+/([^/\"]+\\.log)\"", "\"[…]/$1\""),
+        ("section_(start|end):\\d+", "section_$1:[…]"),
+    ]}, {
+        let mut output_buf = Vec::new();
+        let mut error_buf = Vec::new();
+
+        let ctx = nur_lib::commands::Context {
+            cwd: nurfile_path.parent().unwrap().to_owned(),
+            stdout: &mut output_buf,
+            stderr: &mut error_buf,
+        };
+
+        let result = command.run(ctx);
+        let golden = prep_output(&output_buf, &error_buf, result);
+        insta::assert_snapshot!(snapshot_name, golden);
+    });
 }
 
 fn run_config(
+    task_command: Task,
     nurfile_path: &Path,
     stdout: &mut dyn Write,
     stderr: &mut dyn Write,
@@ -49,10 +676,13 @@ fn run_config(
         stderr,
     };
 
-    let task_command = nur_lib::commands::Task {
-        dry_run: false,
+    task_command.run(ctx)
+}
+
+fn task_for(nurfile_path: &Path) -> Task {
+    Task {
         nur_file: Some(nurfile_path.to_owned()),
-        task_names: Default::default(),
+        color: ColorChoice::Never,
         output_override: Some(OutputOptions {
             prefix: nur_lib::nurfile::PrefixStyle::Aligned,
             style: nur_lib::nurfile::OutputStyle::Grouped {
@@ -61,11 +691,14 @@ fn run_config(
                 separator_last: Some("╰".to_string()),
                 deterministic: true,
                 only_on_failure: false,
+                memory_limit: nur_lib::output::grouped::DEFAULT_MEMORY_LIMIT,
+                max_lines: None,
+                failure_tail: None,
             },
+            ..Default::default()
         }),
-    };
-
-    task_command.run(ctx)
+        ..Default::default()
+    }
 }
 
 fn prep_output(stdout: &[u8], stderr: &[u8], result: Result<()>) -> String {
@@ -0,0 +1,7 @@
+#include <stdio.h>
+
+int main(void) {
+    int unused;
+    printf("%d\n", missing);
+    return 0;
+}